@@ -0,0 +1,379 @@
+use crate::*;
+use std::sync::OnceLock;
+
+/// Set of squares, bit `y * 8 + x` is the square `Square { x, y }`
+pub type Bitboard = u64;
+
+impl Square {
+    /// Returns the index of the square's bit in a bitboard
+    pub fn index(&self) -> usize {
+        self.y as usize * 8 + self.x as usize
+    }
+
+    pub fn from_index(index: usize) -> Square {
+        Square {
+            x: (index % 8) as u8,
+            y: (index / 8) as u8,
+        }
+    }
+
+    pub fn bitboard(&self) -> Bitboard {
+        1 << self.index()
+    }
+}
+
+/// Iterates over the squares of a bitboard, from a1 to h8
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = Square> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+
+        let index: usize = bitboard.trailing_zeros() as usize;
+        bitboard &= bitboard - 1;
+        Some(Square::from_index(index))
+    })
+}
+
+/// Pieces on the board as one bitboard per kind of piece and one per color
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq)]
+pub struct Board {
+    /// Indexed by `PieceType::kind_index`
+    pub kinds: [Bitboard; 6],
+    /// Indexed by `PieceColor::index`
+    pub colors: [Bitboard; 2],
+}
+
+impl Board {
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    /// Returns the squares with pieces of the given type
+    pub fn pieces_of(&self, piece_type: PieceType) -> Bitboard {
+        match piece_type.color() {
+            Some(color) => self.kinds[piece_type.kind_index()] & self.colors[color.index()],
+            None => !self.occupied(),
+        }
+    }
+
+    /// Returns the piece type on the given square, PieceType::None if it's empty
+    pub fn piece_at(&self, square: Square) -> PieceType {
+        let bit: Bitboard = square.bitboard();
+        let color: PieceColor = if self.colors[0] & bit != 0 {
+            PieceColor::White
+        } else if self.colors[1] & bit != 0 {
+            PieceColor::Black
+        } else {
+            return PieceType::None;
+        };
+
+        let kind: usize = self
+            .kinds
+            .iter()
+            .position(|kind| kind & bit != 0)
+            .expect("Occupied square should have a kind of piece");
+        PieceType::from_kind_index(kind, color)
+    }
+
+    /// Puts the piece on the square, replacing whatever stood there
+    pub fn set(&mut self, square: Square, piece_type: PieceType) {
+        self.remove(square);
+
+        if let Some(color) = piece_type.color() {
+            let bit: Bitboard = square.bitboard();
+            self.kinds[piece_type.kind_index()] |= bit;
+            self.colors[color.index()] |= bit;
+        }
+    }
+
+    pub fn remove(&mut self, square: Square) {
+        let bit: Bitboard = !square.bitboard();
+        for kind in self.kinds.iter_mut() {
+            *kind &= bit;
+        }
+        for color in self.colors.iter_mut() {
+            *color &= bit;
+        }
+    }
+
+    /// Returns the squares attacked by the piece standing on the given square
+    pub fn attacks_from(&self, square: Square, piece_type: PieceType) -> Bitboard {
+        let occupied: Bitboard = self.occupied();
+        match piece_type {
+            PieceType::PawnWhite => pawn_attacks(PieceColor::White, square),
+            PieceType::PawnBlack => pawn_attacks(PieceColor::Black, square),
+            PieceType::KnightWhite | PieceType::KnightBlack => knight_attacks(square),
+            PieceType::BishopWhite | PieceType::BishopBlack => bishop_attacks(square, occupied),
+            PieceType::RookWhite | PieceType::RookBlack => rook_attacks(square, occupied),
+            PieceType::QueenWhite | PieceType::QueenBlack => {
+                bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+            }
+            PieceType::KingWhite | PieceType::KingBlack => king_attacks(square),
+            PieceType::None => 0,
+        }
+    }
+
+    /// Returns true if any piece of the given color attacks the given square
+    pub fn is_square_attacked(&self, square: Square, by: PieceColor) -> bool {
+        let occupied: Bitboard = self.occupied();
+        let them: Bitboard = self.colors[by.index()];
+        let kind = |piece_type: PieceType| self.kinds[piece_type.kind_index()] & them;
+
+        // Attacks are symmetric, a knight on the square attacks the knights attacking it
+        pawn_attacks(by.opposite(), square) & kind(PieceType::PawnWhite) != 0
+            || knight_attacks(square) & kind(PieceType::KnightWhite) != 0
+            || king_attacks(square) & kind(PieceType::KingWhite) != 0
+            || bishop_attacks(square, occupied)
+                & (kind(PieceType::BishopWhite) | kind(PieceType::QueenWhite))
+                != 0
+            || rook_attacks(square, occupied)
+                & (kind(PieceType::RookWhite) | kind(PieceType::QueenWhite))
+                != 0
+    }
+}
+
+/// https://www.chessprogramming.org/Magic_Bitboards
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: Bitboard) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct AttackTables {
+    pawn: [[Bitboard; 64]; 2],
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    bishop_magics: Vec<Magic>,
+    rook_magics: Vec<Magic>,
+    sliding: Vec<Bitboard>,
+}
+
+static ATTACK_TABLES: OnceLock<AttackTables> = OnceLock::new();
+
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+fn tables() -> &'static AttackTables {
+    ATTACK_TABLES.get_or_init(AttackTables::new)
+}
+
+pub fn pawn_attacks(color: PieceColor, square: Square) -> Bitboard {
+    tables().pawn[color.index()][square.index()]
+}
+
+pub fn knight_attacks(square: Square) -> Bitboard {
+    tables().knight[square.index()]
+}
+
+pub fn king_attacks(square: Square) -> Bitboard {
+    tables().king[square.index()]
+}
+
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables: &AttackTables = tables();
+    tables.sliding[tables.bishop_magics[square.index()].index(occupied)]
+}
+
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables: &AttackTables = tables();
+    tables.sliding[tables.rook_magics[square.index()].index(occupied)]
+}
+
+/// Returns the square shifted by the offset, None if it would leave the board
+fn offset_square(square: Square, (dx, dy): (i8, i8)) -> Option<Square> {
+    let x: i8 = square.x as i8 + dx;
+    let y: i8 = square.y as i8 + dy;
+    if (0..8).contains(&x) && (0..8).contains(&y) {
+        Some(Square {
+            x: x as u8,
+            y: y as u8,
+        })
+    } else {
+        None
+    }
+}
+
+/// Walks the rays from the square until they hit a piece or the edge of the board<br />
+/// Slow, only used to fill the magic tables
+fn sliding_attacks(square: Square, occupied: Bitboard, directions: &[(i8, i8)]) -> Bitboard {
+    let mut attacks: Bitboard = 0;
+    for direction in directions {
+        let mut current: Square = square;
+        while let Some(next) = offset_square(current, *direction) {
+            attacks |= next.bitboard();
+            if occupied & next.bitboard() != 0 {
+                break;
+            }
+            current = next;
+        }
+    }
+
+    attacks
+}
+
+/// Squares whose occupancy matters for the slider, the last square of each ray never blocks anything
+fn relevant_occupancy(square: Square, directions: &[(i8, i8)]) -> Bitboard {
+    let mut mask: Bitboard = 0;
+    for direction in directions {
+        let mut current: Square = square;
+        while let Some(next) = offset_square(current, *direction) {
+            if offset_square(next, *direction).is_none() {
+                break;
+            }
+            mask |= next.bitboard();
+            current = next;
+        }
+    }
+
+    mask
+}
+
+/// Xorshift generator, magics are searched with a fixed seed so the tables are always the same
+pub(crate) struct Random(pub(crate) u64);
+
+impl Random {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Magics with few bits set are found much faster
+    fn sparse(&mut self) -> u64 {
+        self.next() & self.next() & self.next()
+    }
+}
+
+/// Finds a magic for every square and fills the shared table of sliding attacks
+fn find_magics(
+    directions: &[(i8, i8)],
+    sliding: &mut Vec<Bitboard>,
+    random: &mut Random,
+) -> Vec<Magic> {
+    (0..64)
+        .map(|index| {
+            let square: Square = Square::from_index(index);
+            let mask: Bitboard = relevant_occupancy(square, directions);
+            let bits: u32 = mask.count_ones();
+
+            // Every subset of the mask, https://www.chessprogramming.org/Traversing_Subsets_of_a_Set
+            let mut occupancies: Vec<Bitboard> = Vec::new();
+            let mut subset: Bitboard = 0;
+            loop {
+                occupancies.push(subset);
+                subset = subset.wrapping_sub(mask) & mask;
+                if subset == 0 {
+                    break;
+                }
+            }
+            let attacks: Vec<Bitboard> = occupancies
+                .iter()
+                .map(|occupied| sliding_attacks(square, *occupied, directions))
+                .collect();
+
+            let offset: usize = sliding.len();
+            // Entries are valid only if they were written in the current attempt
+            let mut table: Vec<(u32, Bitboard)> = vec![(0, 0); 1 << bits];
+            let mut attempt: u32 = 0;
+            loop {
+                let magic: Magic = Magic {
+                    mask,
+                    magic: random.sparse(),
+                    shift: 64 - bits,
+                    offset: 0,
+                };
+
+                // Magics that don't spread the mask into the top bits can't work
+                if (mask.wrapping_mul(magic.magic) >> 56).count_ones() < 6 {
+                    continue;
+                }
+
+                attempt += 1;
+                let is_valid: bool =
+                    occupancies
+                        .iter()
+                        .zip(attacks.iter())
+                        .all(|(occupied, attacks)| {
+                            let entry: &mut (u32, Bitboard) = &mut table[magic.index(*occupied)];
+                            if entry.0 == attempt {
+                                entry.1 == *attacks
+                            } else {
+                                *entry = (attempt, *attacks);
+                                true
+                            }
+                        });
+
+                if is_valid {
+                    sliding.extend(table.iter().map(|(_, attacks)| attacks));
+                    return Magic { offset, ..magic };
+                }
+            }
+        })
+        .collect()
+}
+
+impl AttackTables {
+    fn new() -> Self {
+        let mut pawn: [[Bitboard; 64]; 2] = [[0; 64]; 2];
+        let mut knight: [Bitboard; 64] = [0; 64];
+        let mut king: [Bitboard; 64] = [0; 64];
+
+        for index in 0..64 {
+            let square: Square = Square::from_index(index);
+            let targets = |offsets: &[(i8, i8)]| {
+                offsets
+                    .iter()
+                    .filter_map(|offset| offset_square(square, *offset))
+                    .fold(0, |bitboard, target| bitboard | target.bitboard())
+            };
+
+            pawn[PieceColor::White.index()][index] = targets(&[(-1, 1), (1, 1)]);
+            pawn[PieceColor::Black.index()][index] = targets(&[(-1, -1), (1, -1)]);
+            knight[index] = targets(&KNIGHT_JUMPS);
+            king[index] = targets(&KING_STEPS);
+        }
+
+        let mut random: Random = Random(0x9E37_79B9_7F4A_7C15);
+        let mut sliding: Vec<Bitboard> = Vec::new();
+        let bishop_magics: Vec<Magic> = find_magics(&BISHOP_DIRECTIONS, &mut sliding, &mut random);
+        let rook_magics: Vec<Magic> = find_magics(&ROOK_DIRECTIONS, &mut sliding, &mut random);
+
+        Self {
+            pawn,
+            knight,
+            king,
+            bishop_magics,
+            rook_magics,
+            sliding,
+        }
+    }
+}
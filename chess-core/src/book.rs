@@ -0,0 +1,250 @@
+use crate::*;
+use std::path::Path;
+
+/// Size of an entry in a Polyglot book file
+const ENTRY_SIZE: usize = 16;
+
+/// Move of a Polyglot book for a position, entries are sorted by key<br />
+/// http://hgm.nubati.net/book_format.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BookEntry {
+    /// Polyglot key of the position, the same as `Position::hash`
+    pub key: u64,
+    /// Move packed as to file, to rank, from file and from rank in 3 bits each, then the promotion
+    pub raw_move: u16,
+    /// How often the move should be played relative to the others of the position
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    /// Resolves the entry's move against the legal moves of the position<br />
+    /// Polyglot writes castling as the king taking its own rook, e.g. `e1h1`<br />
+    /// Returns None if it isn't a legal move
+    pub fn to_move(&self, position: &Position) -> Option<Move> {
+        let square = |bits: u16| Square {
+            x: (bits & 7) as u8,
+            y: (bits >> 3 & 7) as u8,
+        };
+        let from: Square = square(self.raw_move >> 6);
+        let mut to: Square = square(self.raw_move);
+        let promotion: Option<PieceType> = match self.raw_move >> 12 & 7 {
+            0 => None,
+            kind => Some(PieceType::from_kind_index(
+                kind as usize,
+                position.color_to_move,
+            )),
+        };
+
+        let piece_type: PieceType = position.board.piece_at(from);
+        if piece_type.is_king()
+            && from.x == 4
+            && from.y == to.y
+            && position.board.piece_at(to)
+                == PieceType::RookWhite.with_color(position.color_to_move)
+        {
+            to.x = if to.x > from.x { 6 } else { 2 };
+        }
+
+        generate_legal_moves(position)
+            .into_iter()
+            .find(|mv| mv.from == from && mv.to == to && mv.promotion == promotion)
+    }
+}
+
+/// Why an opening book couldn't be read
+#[derive(Debug)]
+pub enum BookError {
+    Io(std::io::Error),
+    /// File size in bytes isn't a multiple of the entry size
+    Truncated(usize),
+}
+
+impl std::fmt::Display for BookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BookError::Io(error) => write!(f, "{error}"),
+            BookError::Truncated(size) => write!(
+                f,
+                "{size} bytes is not a whole number of {ENTRY_SIZE} byte entries"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+impl From<std::io::Error> for BookError {
+    fn from(error: std::io::Error) -> Self {
+        BookError::Io(error)
+    }
+}
+
+/// Polyglot opening book, read whole into memory
+#[derive(Clone, Debug, Default)]
+pub struct OpeningBook {
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    /// Reads a Polyglot `.bin` book
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BookError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(BookError::Truncated(bytes.len()));
+        }
+
+        // Books are sorted already, sorting again only guards the lookup against broken ones
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(BookEntry::from_bytes)
+            .collect();
+        entries.sort_by_key(|entry| entry.key);
+        Ok(Self { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entries of the position, empty if it's out of book
+    pub fn entries(&self, position: &Position) -> &[BookEntry] {
+        let key: u64 = position.hash();
+        let start: usize = self.entries.partition_point(|entry| entry.key < key);
+        let end: usize = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// Returns the legal book moves of the position with their weights
+    pub fn moves(&self, position: &Position) -> Vec<(Move, u16)> {
+        self.entries(position)
+            .iter()
+            .filter_map(|entry| Some((entry.to_move(position)?, entry.weight)))
+            .collect()
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        !self.moves(position).is_empty()
+    }
+
+    /// Picks a book move at random, moves with a higher weight more often<br />
+    /// `random` is any random number, moves with weight 0 are never picked
+    pub fn choose_move(&self, position: &Position, random: u64) -> Option<Move> {
+        let moves: Vec<(Move, u16)> = self.moves(position);
+        let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut pick: u64 = random % total;
+        for (mv, weight) in moves {
+            if pick < weight as u64 {
+                return Some(mv);
+            }
+            pick -= weight as u64;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_bytes(position: &Position, uci: &str, weight: u16) -> Vec<u8> {
+        let square = |name: &str| -> u16 {
+            let square: Square = name.parse().unwrap();
+            square.y as u16 * 8 + square.x as u16
+        };
+        let raw_move: u16 = square(&uci[0..2]) << 6 | square(&uci[2..4]);
+
+        let mut bytes: Vec<u8> = position.hash().to_be_bytes().to_vec();
+        bytes.extend(raw_move.to_be_bytes());
+        bytes.extend(weight.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn reads_book_moves() {
+        let start: Position = Position::new();
+        let after_e4: Position = start.make_move(uci_to_move(&start, "e2e4").unwrap());
+        let bytes: Vec<u8> = [
+            entry_bytes(&after_e4, "c7c5", 3),
+            entry_bytes(&start, "e2e4", 2),
+            entry_bytes(&start, "d2d4", 1),
+            entry_bytes(&start, "e2e5", 5),
+        ]
+        .concat();
+        let book: OpeningBook = OpeningBook::from_bytes(&bytes).unwrap();
+
+        assert_eq!(book.len(), 4);
+        assert_eq!(book.entries(&start).len(), 3);
+        // Illegal move is skipped
+        let moves: Vec<String> = book
+            .moves(&start)
+            .iter()
+            .map(|(mv, _)| mv.to_uci())
+            .collect();
+        assert_eq!(moves, ["e2e4", "d2d4"]);
+        assert!(book.contains(&after_e4));
+        assert!(!book.contains(&Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap()));
+
+        assert!(matches!(
+            OpeningBook::from_bytes(&bytes[..20]),
+            Err(BookError::Truncated(20))
+        ));
+    }
+
+    #[test]
+    fn picks_moves_by_weight() {
+        let start: Position = Position::new();
+        let bytes: Vec<u8> = [
+            entry_bytes(&start, "e2e4", 3),
+            entry_bytes(&start, "d2d4", 1),
+            entry_bytes(&start, "g1f3", 0),
+        ]
+        .concat();
+        let book: OpeningBook = OpeningBook::from_bytes(&bytes).unwrap();
+
+        let picks: Vec<String> = (0..4)
+            .map(|random| book.choose_move(&start, random).unwrap().to_uci())
+            .collect();
+        assert_eq!(picks, ["e2e4", "e2e4", "e2e4", "d2d4"]);
+    }
+
+    #[test]
+    fn converts_castling() {
+        let position: Position =
+            Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let bytes: Vec<u8> = [
+            entry_bytes(&position, "e1h1", 1),
+            entry_bytes(&position, "e1a1", 1),
+        ]
+        .concat();
+        let book: OpeningBook = OpeningBook::from_bytes(&bytes).unwrap();
+
+        let moves: Vec<String> = book
+            .moves(&position)
+            .iter()
+            .map(|(mv, _)| mv.to_uci())
+            .collect();
+        assert_eq!(moves, ["e1g1", "e1c1"]);
+    }
+}
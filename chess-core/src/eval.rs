@@ -0,0 +1,382 @@
+use crate::*;
+
+/// Value of a pawn in the units returned by [`evaluate`]
+pub const PAWN_VALUE: i32 = 100;
+
+/// Game phase of the starting position, knights and bishops count 1, rooks 2 and queens 4
+const MAX_PHASE: i32 = 24;
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+
+/// Material of pawn, knight, bishop, rook, queen and king in the middlegame and the endgame<br />
+/// Values and piece-square tables are PeSTO's, https://www.chessprogramming.org/PeSTO%27s_Evaluation_Function
+const MG_VALUES: [i32; 6] = [82, 337, 365, 477, 1025, 0];
+const EG_VALUES: [i32; 6] = [94, 281, 297, 512, 936, 0];
+
+/// Piece-square tables from White's side, a8 first and h1 last
+#[rustfmt::skip]
+const MG_TABLES: [[i32; 64]; 6] = [
+    [
+          0,   0,   0,   0,   0,   0,  0,   0,
+         98, 134,  61,  95,  68, 126, 34, -11,
+         -6,   7,  26,  31,  65,  56, 25, -20,
+        -14,  13,   6,  21,  23,  12, 17, -23,
+        -27,  -2,  -5,  12,  17,   6, 10, -25,
+        -26,  -4,  -4, -10,   3,   3, 33, -12,
+        -35,  -1, -20, -23, -15,  24, 38, -22,
+          0,   0,   0,   0,   0,   0,  0,   0,
+    ],
+    [
+        -167, -89, -34, -49,  61, -97, -15, -107,
+         -73, -41,  72,  36,  23,  62,   7,  -17,
+         -47,  60,  37,  65,  84, 129,  73,   44,
+          -9,  17,  19,  53,  37,  69,  18,   22,
+         -13,   4,  16,  13,  28,  19,  21,   -8,
+         -23,  -9,  12,  10,  19,  17,  25,  -16,
+         -29, -53, -12,  -3,  -1,  18, -14,  -19,
+        -105, -21, -58, -33, -17, -28, -19,  -23,
+    ],
+    [
+        -29,   4, -82, -37, -25, -42,   7,  -8,
+        -26,  16, -18, -13,  30,  59,  18, -47,
+        -16,  37,  43,  40,  35,  50,  37,  -2,
+         -4,   5,  19,  50,  37,  37,   7,  -2,
+         -6,  13,  13,  26,  34,  12,  10,   4,
+          0,  15,  15,  15,  14,  27,  18,  10,
+          4,  15,  16,   0,   7,  21,  33,   1,
+        -33,  -3, -14, -21, -13, -12, -39, -21,
+    ],
+    [
+         32,  42,  32,  51, 63,  9,  31,  43,
+         27,  32,  58,  62, 80, 67,  26,  44,
+         -5,  19,  26,  36, 17, 45,  61,  16,
+        -24, -11,   7,  26, 24, 35,  -8, -20,
+        -36, -26, -12,  -1,  9, -7,   6, -23,
+        -45, -25, -16, -17,  3,  0,  -5, -33,
+        -44, -16, -20,  -9, -1, 11,  -6, -71,
+        -19, -13,   1,  17, 16,  7, -37, -26,
+    ],
+    [
+        -28,   0,  29,  12,  59,  44,  43,  45,
+        -24, -39,  -5,   1, -16,  57,  28,  54,
+        -13, -17,   7,   8,  29,  56,  47,  57,
+        -27, -27, -16, -16,  -1,  17,  -2,   1,
+         -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+        -14,   2, -11,  -2,  -5,   2,  14,   5,
+        -35,  -8,  11,   2,   8,  15,  -3,   1,
+         -1, -18,  -9,  10, -15, -25, -31, -50,
+    ],
+    [
+        -65,  23,  16, -15, -56, -34,   2,  13,
+         29,  -1, -20,  -7,  -8,  -4, -38, -29,
+         -9,  24,   2, -16, -20,   6,  22, -22,
+        -17, -20, -12, -27, -30, -25, -14, -36,
+        -49,  -1, -27, -39, -46, -44, -33, -51,
+        -14, -14, -22, -46, -44, -30, -15, -27,
+          1,   7,  -8, -64, -43, -16,   9,   8,
+        -15,  36,  12, -54,   8, -28,  24,  14,
+    ],
+];
+
+#[rustfmt::skip]
+const EG_TABLES: [[i32; 64]; 6] = [
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+        178, 173, 158, 134, 147, 132, 165, 187,
+         94, 100,  85,  67,  56,  53,  82,  84,
+         32,  24,  13,   5,  -2,   4,  17,  17,
+         13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+          4,   7,  -6,   1,   0,  -5,  -1,  -8,
+         13,   8,   8,  10,  13,   0,   2,  -7,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    [
+        -58, -38, -13, -28, -31, -27, -63, -99,
+        -25,  -8, -25,  -2,  -9, -25, -24, -52,
+        -24, -20,  10,   9,  -1,  -9, -19, -41,
+        -17,   3,  22,  22,  22,  11,   8, -18,
+        -18,  -6,  16,  25,  16,  17,   4, -18,
+        -23,  -3,  -1,  15,  10,  -3, -20, -22,
+        -42, -20, -10,  -5,  -2, -20, -23, -44,
+        -29, -51, -23, -15, -22, -18, -50, -64,
+    ],
+    [
+        -14, -21, -11,  -8, -7,  -9, -17, -24,
+         -8,  -4,   7, -12, -3, -13,  -4, -14,
+          2,  -8,   0,  -1, -2,   6,   0,   4,
+         -3,   9,  12,   9, 14,  10,   3,   2,
+         -6,   3,  13,  19,  7,  10,  -3,  -9,
+        -12,  -3,   8,  10, 13,   3,  -7, -15,
+        -14, -18,  -7,  -1,  4,  -9, -15, -27,
+        -23,  -9, -23,  -5, -9, -16,  -5, -17,
+    ],
+    [
+        13, 10, 18, 15, 12,  12,   8,   5,
+        11, 13, 13, 11, -3,   3,   8,   3,
+         7,  7,  7,  5,  4,  -3,  -5,  -3,
+         4,  3, 13,  1,  2,   1,  -1,   2,
+         3,  5,  8,  4, -5,  -6,  -8, -11,
+        -4,  0, -5, -1, -7, -12,  -8, -16,
+        -6, -6,  0,  2, -9,  -9, -11,  -3,
+        -9,  2,  3, -1, -5, -13,   4, -20,
+    ],
+    [
+         -9,  22,  22,  27,  27,  19,  10,  20,
+        -17,  20,  32,  41,  58,  25,  30,   0,
+        -20,   6,   9,  49,  47,  35,  19,   9,
+          3,  22,  24,  45,  57,  40,  57,  36,
+        -18,  28,  19,  47,  31,  34,  39,  23,
+        -16, -27,  15,   6,   9,  17,  10,   5,
+        -22, -23, -30, -16, -16, -23, -36, -32,
+        -33, -28, -22, -43,  -5, -32, -20, -41,
+    ],
+    [
+        -74, -35, -18, -18, -11,  15,   4, -17,
+        -12,  17,  14,  17,  17,  38,  23,  11,
+         10,  17,  23,  15,  20,  45,  44,  13,
+         -8,  22,  24,  27,  26,  33,  26,   3,
+        -18,  -4,  21,  24,  27,  23,   9, -11,
+        -19,  -3,  11,  21,  23,  16,   7,  -9,
+        -27, -11,   4,  13,  14,   4,  -5, -17,
+        -53, -34, -21, -11, -28, -14, -24, -43,
+    ],
+];
+
+/// Bonus for each square a knight, bishop, rook or queen attacks that isn't taken by an own piece,
+/// counted from the average number of such squares
+const MOBILITY: [(i32, i32, i32); 6] = [
+    (0, 0, 0),
+    (4, 4, 4),
+    (5, 5, 6),
+    (2, 4, 7),
+    (1, 2, 13),
+    (0, 0, 0),
+];
+
+const DOUBLED_PAWN: (i32, i32) = (-10, -20);
+const ISOLATED_PAWN: (i32, i32) = (-15, -10);
+/// Bonus of a passed pawn by the number of rows it has advanced
+const PASSED_PAWN: [(i32, i32); 8] = [
+    (0, 0),
+    (5, 10),
+    (10, 20),
+    (15, 35),
+    (25, 60),
+    (40, 90),
+    (60, 130),
+    (0, 0),
+];
+const BISHOP_PAIR: (i32, i32) = (30, 50);
+/// Bonus of each own pawn in front of the king, middlegame only
+const PAWN_SHIELD: i32 = 12;
+/// Penalty of each square next to the king the opponent attacks, middlegame only
+const KING_ZONE_ATTACK: i32 = -8;
+
+const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+
+/// Returns the squares of the file and its neighbouring files
+fn adjacent_files(x: u8) -> Bitboard {
+    let file: Bitboard = FILE_A << x;
+    ((file << 1) & !FILE_A) | ((file >> 1) & !(FILE_A << 7))
+}
+
+/// Returns the squares in front of the row from the color's point of view
+fn rows_ahead(color: PieceColor, y: u8) -> Bitboard {
+    match color {
+        PieceColor::White if y < 7 => !0 << ((y + 1) * 8),
+        PieceColor::Black if y > 0 => !0 >> ((8 - y) * 8),
+        _ => 0,
+    }
+}
+
+/// Middlegame and endgame score of one side, positive is good for it
+fn evaluate_side(board: &Board, color: PieceColor) -> (i32, i32) {
+    let own: Bitboard = board.colors[color.index()];
+    let opponent: PieceColor = color.opposite();
+    let own_pawns: Bitboard = board.kinds[0] & own;
+    let opponent_pawns: Bitboard = board.kinds[0] & board.colors[opponent.index()];
+    let (mut mg, mut eg): (i32, i32) = (0, 0);
+
+    for square in squares(own) {
+        let piece_type: PieceType = board.piece_at(square);
+        let kind: usize = piece_type.kind_index();
+
+        // Tables are from White's side with a8 first
+        let table_index: usize = match color {
+            PieceColor::White => square.index() ^ 56,
+            PieceColor::Black => square.index(),
+        };
+        mg += MG_VALUES[kind] + MG_TABLES[kind][table_index];
+        eg += EG_VALUES[kind] + EG_TABLES[kind][table_index];
+
+        let (mg_weight, eg_weight, average): (i32, i32, i32) = MOBILITY[kind];
+        if mg_weight != 0 {
+            let mobility: i32 = (board.attacks_from(square, piece_type) & !own).count_ones() as i32;
+            mg += mg_weight * (mobility - average);
+            eg += eg_weight * (mobility - average);
+        }
+
+        if piece_type.is_pawn() {
+            let file: Bitboard = FILE_A << square.x;
+            let ahead: Bitboard = rows_ahead(color, square.y);
+
+            if own_pawns & file & ahead != 0 {
+                mg += DOUBLED_PAWN.0;
+                eg += DOUBLED_PAWN.1;
+            }
+            if own_pawns & adjacent_files(square.x) == 0 {
+                mg += ISOLATED_PAWN.0;
+                eg += ISOLATED_PAWN.1;
+            }
+            if opponent_pawns & (file | adjacent_files(square.x)) & ahead == 0 {
+                let advanced: usize = match color {
+                    PieceColor::White => square.y as usize,
+                    PieceColor::Black => 7 - square.y as usize,
+                };
+                mg += PASSED_PAWN[advanced].0;
+                eg += PASSED_PAWN[advanced].1;
+            }
+        }
+    }
+
+    if (board.kinds[2] & own).count_ones() >= 2 {
+        mg += BISHOP_PAIR.0;
+        eg += BISHOP_PAIR.1;
+    }
+
+    // King safety counts in the middlegame score only, tapering fades it out as pieces come off
+    if let Some(king) = squares(board.kinds[5] & own).next() {
+        let zone: Bitboard = king_attacks(king);
+
+        // Pawns up to two rows in front of the king and on its neighbouring files
+        let two_rows: Bitboard = match color {
+            PieceColor::White => zone | zone << 8,
+            PieceColor::Black => zone | zone >> 8,
+        };
+        let shield: Bitboard =
+            (FILE_A << king.x | adjacent_files(king.x)) & rows_ahead(color, king.y) & two_rows;
+        mg += PAWN_SHIELD * (shield & own_pawns).count_ones() as i32;
+        mg += KING_ZONE_ATTACK
+            * squares(zone)
+                .filter(|square| board.is_square_attacked(*square, opponent))
+                .count() as i32;
+    }
+
+    (mg, eg)
+}
+
+/// Returns the score of the position from the side to move's point of view, in centipawns<br />
+/// Middlegame and endgame scores are blended by the material left on the board,
+/// https://www.chessprogramming.org/Tapered_Eval
+pub fn evaluate(position: &Position) -> i32 {
+    let board: &Board = &position.board;
+    let (white_mg, white_eg): (i32, i32) = evaluate_side(board, PieceColor::White);
+    let (black_mg, black_eg): (i32, i32) = evaluate_side(board, PieceColor::Black);
+
+    let phase: i32 = (0..6)
+        .map(|kind| PHASE_WEIGHTS[kind] * board.kinds[kind].count_ones() as i32)
+        .sum::<i32>()
+        .min(MAX_PHASE);
+    let score: i32 =
+        ((white_mg - black_mg) * phase + (white_eg - black_eg) * (MAX_PHASE - phase)) / MAX_PHASE;
+
+    match position.color_to_move {
+        PieceColor::White => score,
+        PieceColor::Black => -score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(fen: &str) -> i32 {
+        evaluate(&Position::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn start_position_is_balanced() {
+        assert_eq!(evaluate(&Position::new()), 0);
+        assert_eq!(
+            eval("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1"),
+            0
+        );
+    }
+
+    #[test]
+    fn is_symmetric() {
+        // Same position with colors swapped and the board mirrored
+        let white: i32 =
+            eval("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        let black: i32 =
+            eval("rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4");
+        assert_eq!(white, black);
+    }
+
+    #[test]
+    fn prefers_more_material() {
+        assert!(eval("4k3/8/8/8/8/8/8/3QK3 w - - 0 1") > 8 * PAWN_VALUE);
+        assert!(eval("4k3/8/8/8/8/8/8/3QK3 b - - 0 1") < -8 * PAWN_VALUE);
+    }
+
+    /// Returns how much two changes to a position score together beyond what each scores alone<br />
+    /// Material, piece-square tables and everything else counted piece by piece cancel out,
+    /// only terms depending on both changes are left
+    fn interaction(both: &str, first: &str, second: &str, neither: &str) -> i32 {
+        eval(both) - eval(first) - eval(second) + eval(neither)
+    }
+
+    #[test]
+    fn penalizes_doubled_pawns() {
+        // Pawn on d2 is doubled only behind the one on d3
+        let doubled: i32 = interaction(
+            "4k3/8/8/8/8/3P4/3P4/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/3P4/4K3 w - - 0 1",
+            "4k3/8/8/8/8/3P4/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        );
+        // Only kings are left, so the endgame score counts
+        assert!(doubled < 0);
+        assert_eq!(doubled, DOUBLED_PAWN.1);
+    }
+
+    #[test]
+    fn penalizes_isolated_pawns() {
+        // Pawns on c2 and d2 are each isolated only without the other
+        let connected: i32 = interaction(
+            "4k3/8/8/8/8/8/2PP4/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/2P5/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/3P4/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        );
+        assert!(connected > 0);
+        assert_eq!(connected, -2 * ISOLATED_PAWN.1);
+    }
+
+    #[test]
+    fn rewards_passed_pawns() {
+        // Pawns on c5 and c7 are each passed only without the other
+        let blocked: i32 = interaction(
+            "4k3/2p5/8/2P5/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/2P5/8/8/8/4K3 w - - 0 1",
+            "4k3/2p5/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        );
+        // White loses the bonus of a pawn 4 rows up, Black the one of a pawn 1 row up
+        assert!(blocked < 0);
+        assert_eq!(blocked, -PASSED_PAWN[4].1 + PASSED_PAWN[1].1);
+    }
+
+    #[test]
+    fn rewards_bishop_pair() {
+        // Bishop on f1 instead of a knight makes a pair only next to the bishop on c1
+        let pair: i32 = interaction(
+            "4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1KN2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2N1KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2N1KN2 w - - 0 1",
+        );
+        // Two minor pieces are nearly an endgame, each evaluation rounds on its own
+        assert!(pair > 0);
+        assert!((BISHOP_PAIR.0..=BISHOP_PAIR.1).contains(&pair), "{pair}");
+    }
+}
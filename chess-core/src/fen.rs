@@ -0,0 +1,507 @@
+use crate::*;
+
+/// Why a FEN string couldn't be turned into a position
+#[derive(Clone, Debug, PartialEq)]
+pub enum FenError {
+    /// FEN has 6 fields separated by spaces
+    WrongFieldCount(usize),
+    /// Piece placement has 8 ranks separated by `/`
+    WrongRankCount(usize),
+    InvalidPiece(char),
+    /// Rank, counted from 8 like in FEN, doesn't describe exactly 8 squares
+    WrongRankLength {
+        rank: u8,
+    },
+    WrongKingCount {
+        color: PieceColor,
+        count: u32,
+    },
+    PawnOnBackRank(Square),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    /// Castling right for a king or rook that isn't on its starting square
+    ImpossibleCastling(char),
+    InvalidEnPassant(String),
+    /// En passant square that no pawn could have just passed
+    ImpossibleEnPassant(Square),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    /// Player who just moved can't have left their king in check
+    OpponentInCheck,
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count) => write!(f, "expected 6 fields, found {count}"),
+            FenError::WrongRankCount(count) => write!(f, "expected 8 ranks, found {count}"),
+            FenError::InvalidPiece(c) => write!(f, "invalid piece '{c}'"),
+            FenError::WrongRankLength { rank } => {
+                write!(f, "rank {rank} doesn't have exactly 8 squares")
+            }
+            FenError::WrongKingCount { color, count } => {
+                write!(f, "expected one {color:?} king, found {count}")
+            }
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on back rank at {square}"),
+            FenError::InvalidSideToMove(field) => {
+                write!(f, "invalid side to move '{field}', expected 'w' or 'b'")
+            }
+            FenError::InvalidCastling(field) => {
+                write!(f, "invalid castling availability '{field}'")
+            }
+            FenError::ImpossibleCastling(c) => {
+                write!(
+                    f,
+                    "castling right '{c}' without king and rook on their starting squares"
+                )
+            }
+            FenError::InvalidEnPassant(field) => write!(f, "invalid en passant square '{field}'"),
+            FenError::ImpossibleEnPassant(square) => {
+                write!(f, "no pawn can have just passed en passant square {square}")
+            }
+            FenError::InvalidHalfmoveClock(field) => write!(f, "invalid halfmove clock '{field}'"),
+            FenError::InvalidFullmoveNumber(field) => {
+                write!(
+                    f,
+                    "invalid fullmove number '{field}', expected a number from 1"
+                )
+            }
+            FenError::OpponentInCheck => write!(f, "side not to move is in check"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl std::str::FromStr for Square {
+    type Err = ();
+
+    /// Parses a square in algebraic notation, e.g. `e4`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Ok(Square {
+                x: file - b'a',
+                y: rank - b'1',
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Returns the piece type written as the given FEN letter
+pub fn piece_type_from_fen(c: char) -> Option<PieceType> {
+    match c {
+        'P' => Some(PieceType::PawnWhite),
+        'p' => Some(PieceType::PawnBlack),
+        'R' => Some(PieceType::RookWhite),
+        'r' => Some(PieceType::RookBlack),
+        'N' => Some(PieceType::KnightWhite),
+        'n' => Some(PieceType::KnightBlack),
+        'B' => Some(PieceType::BishopWhite),
+        'b' => Some(PieceType::BishopBlack),
+        'Q' => Some(PieceType::QueenWhite),
+        'q' => Some(PieceType::QueenBlack),
+        'K' => Some(PieceType::KingWhite),
+        'k' => Some(PieceType::KingBlack),
+        _ => None,
+    }
+}
+
+/// Returns the FEN letter of the piece type, None for PieceType::None
+pub fn piece_type_to_fen(piece_type: PieceType) -> Option<char> {
+    match piece_type {
+        PieceType::PawnWhite => Some('P'),
+        PieceType::PawnBlack => Some('p'),
+        PieceType::RookWhite => Some('R'),
+        PieceType::RookBlack => Some('r'),
+        PieceType::KnightWhite => Some('N'),
+        PieceType::KnightBlack => Some('n'),
+        PieceType::BishopWhite => Some('B'),
+        PieceType::BishopBlack => Some('b'),
+        PieceType::QueenWhite => Some('Q'),
+        PieceType::QueenBlack => Some('q'),
+        PieceType::KingWhite => Some('K'),
+        PieceType::KingBlack => Some('k'),
+        PieceType::None => None,
+    }
+}
+
+// https://en.wikipedia.org/wiki/Forsyth%E2%80%93Edwards_Notation
+pub trait ToFEN {
+    fn to_fen(&self) -> String;
+}
+
+impl ToFEN for Position {
+    /// Returns the position as FEN, `Position::from_fen` parses it back to the same position
+    fn to_fen(&self) -> String {
+        let mut fen: String = String::new();
+
+        // FEN starts with the 8th rank
+        for y in (0..8).rev() {
+            let mut empty_squares: u8 = 0;
+            for x in 0..8 {
+                let Some(c) = piece_type_to_fen(self.board.piece_at(Square { x, y })) else {
+                    empty_squares += 1;
+                    continue;
+                };
+
+                if empty_squares > 0 {
+                    fen.push_str(&empty_squares.to_string());
+                    empty_squares = 0;
+                }
+                fen.push(c);
+            }
+            if empty_squares > 0 {
+                fen.push_str(&empty_squares.to_string());
+            }
+
+            if y > 0 {
+                fen.push('/');
+            }
+        }
+
+        let turn_color = match self.color_to_move {
+            PieceColor::White => "w",
+            PieceColor::Black => "b",
+        };
+        let castling = self.castling_rights.to_fen();
+        let en_passant = match self.en_passant_square {
+            Some(square) => square.to_string(),
+            None => "-".to_string(),
+        };
+        fen.push_str(&format!(
+            " {} {castling} {en_passant} {} {}",
+            turn_color, self.halfmove_clock, self.fullmove_number
+        ));
+
+        fen
+    }
+}
+
+impl Position {
+    /// Parses a position from FEN<br />
+    /// https://en.wikipedia.org/wiki/Forsyth%E2%80%93Edwards_Notation
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let [placement, side_to_move, castling, en_passant, halfmove_clock, fullmove_number] =
+            fields[..]
+        else {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        };
+
+        let board: Board = parse_placement(placement)?;
+
+        let color_to_move: PieceColor = match side_to_move {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(FenError::InvalidSideToMove(side_to_move.to_string())),
+        };
+
+        let castling_rights: CastlingRights = parse_castling(castling, &board)?;
+        let en_passant_square: Option<Square> =
+            parse_en_passant(en_passant, &board, color_to_move)?;
+
+        let halfmove_clock: u16 = halfmove_clock
+            .parse()
+            .map_err(|_| FenError::InvalidHalfmoveClock(halfmove_clock.to_string()))?;
+        let fullmove_number: u16 = match fullmove_number.parse() {
+            Ok(number) if number >= 1 => number,
+            _ => return Err(FenError::InvalidFullmoveNumber(fullmove_number.to_string())),
+        };
+
+        let mut position: Position = Position {
+            board,
+            color_to_move,
+            castling_rights,
+            en_passant_square,
+            halfmove_clock,
+            fullmove_number,
+            hash: 0,
+        };
+        position.hash = position.compute_hash();
+
+        if position.is_in_check(color_to_move.opposite()) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        Ok(position)
+    }
+}
+
+/// Parses the piece placement field, ranks go from the 8th to the 1st
+fn parse_placement(placement: &str) -> Result<Board, FenError> {
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    let mut board: Board = Board::default();
+    for (row, rank) in ranks.iter().enumerate() {
+        let y: u8 = 7 - row as u8;
+        let wrong_length: FenError = FenError::WrongRankLength { rank: y + 1 };
+
+        let mut x: u8 = 0;
+        for c in rank.chars() {
+            if let Some(empty_squares) = c.to_digit(10) {
+                if !(1..=8).contains(&empty_squares) {
+                    return Err(FenError::InvalidPiece(c));
+                }
+                x += empty_squares as u8;
+                if x > 8 {
+                    return Err(wrong_length);
+                }
+                continue;
+            }
+
+            let piece_type: PieceType = piece_type_from_fen(c).ok_or(FenError::InvalidPiece(c))?;
+            if x >= 8 {
+                return Err(wrong_length);
+            }
+
+            let square: Square = Square { x, y };
+            if piece_type.is_pawn() && (y == 0 || y == 7) {
+                return Err(FenError::PawnOnBackRank(square));
+            }
+
+            board.set(square, piece_type);
+            x += 1;
+        }
+
+        if x != 8 {
+            return Err(wrong_length);
+        }
+    }
+
+    for color in [PieceColor::White, PieceColor::Black] {
+        let count: u32 = board
+            .pieces_of(PieceType::KingWhite.with_color(color))
+            .count_ones();
+        if count != 1 {
+            return Err(FenError::WrongKingCount { color, count });
+        }
+    }
+
+    Ok(board)
+}
+
+/// Parses the castling availability field, `-` or any of `KQkq` in that order
+fn parse_castling(castling: &str, board: &Board) -> Result<CastlingRights, FenError> {
+    let mut castling_rights: CastlingRights = CastlingRights {
+        white_king_side: false,
+        white_queen_side: false,
+        black_king_side: false,
+        black_queen_side: false,
+    };
+
+    if castling == "-" {
+        return Ok(castling_rights);
+    }
+
+    let invalid: FenError = FenError::InvalidCastling(castling.to_string());
+    if castling.is_empty() {
+        return Err(invalid);
+    }
+
+    // Each letter can appear once and only after the letters before it
+    let mut remaining: &str = "KQkq";
+    for c in castling.chars() {
+        let Some(index) = remaining.find(c) else {
+            return Err(invalid);
+        };
+        remaining = &remaining[index + 1..];
+
+        let (color, rook_x): (PieceColor, u8) = match c {
+            'K' => (PieceColor::White, 7),
+            'Q' => (PieceColor::White, 0),
+            'k' => (PieceColor::Black, 7),
+            _ => (PieceColor::Black, 0),
+        };
+        let home_row: u8 = match color {
+            PieceColor::White => 0,
+            PieceColor::Black => 7,
+        };
+
+        if board.piece_at(Square { x: 4, y: home_row }) != PieceType::KingWhite.with_color(color)
+            || board.piece_at(Square {
+                x: rook_x,
+                y: home_row,
+            }) != PieceType::RookWhite.with_color(color)
+        {
+            return Err(FenError::ImpossibleCastling(c));
+        }
+
+        match c {
+            'K' => castling_rights.white_king_side = true,
+            'Q' => castling_rights.white_queen_side = true,
+            'k' => castling_rights.black_king_side = true,
+            _ => castling_rights.black_queen_side = true,
+        }
+    }
+
+    Ok(castling_rights)
+}
+
+/// Parses the en passant field, the square has to be right behind a pawn that just moved two squares
+fn parse_en_passant(
+    en_passant: &str,
+    board: &Board,
+    color_to_move: PieceColor,
+) -> Result<Option<Square>, FenError> {
+    if en_passant == "-" {
+        return Ok(None);
+    }
+
+    let square: Square = en_passant
+        .parse()
+        .map_err(|_| FenError::InvalidEnPassant(en_passant.to_string()))?;
+
+    // Rank the square is on and the direction the opponent's pawn moved in
+    let (rank, forward): (u8, i8) = match color_to_move {
+        PieceColor::White => (5, -1),
+        PieceColor::Black => (2, 1),
+    };
+    let pawn_square: Square = Square {
+        x: square.x,
+        y: (square.y as i8 + forward) as u8,
+    };
+    let start_square: Square = Square {
+        x: square.x,
+        y: (square.y as i8 - forward) as u8,
+    };
+
+    if square.y != rank
+        || board.piece_at(square) != PieceType::None
+        || board.piece_at(start_square) != PieceType::None
+        || board.piece_at(pawn_square) != PieceType::PawnWhite.with_color(color_to_move.opposite())
+    {
+        return Err(FenError::ImpossibleEnPassant(square));
+    }
+
+    Ok(Some(square))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_start_position() {
+        assert_eq!(
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            Ok(Position::new())
+        );
+    }
+
+    #[test]
+    fn parses_en_passant_and_clocks() {
+        let position: Position =
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b Kq e3 0 3")
+                .unwrap();
+        assert_eq!(position.color_to_move, PieceColor::Black);
+        assert_eq!(position.en_passant_square, Some(Square { x: 4, y: 2 }));
+        assert!(position.castling_rights.white_king_side);
+        assert!(!position.castling_rights.white_queen_side);
+        assert!(!position.castling_rights.black_king_side);
+        assert!(position.castling_rights.black_queen_side);
+        assert_eq!(position.fullmove_number, 3);
+    }
+
+    #[test]
+    fn exports_fen_that_parses_back() {
+        assert_eq!(
+            Position::new().to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b Kq e3 0 3",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            let position: Position = Position::from_fen(fen).unwrap();
+            assert_eq!(position.to_fen(), fen);
+            assert_eq!(Position::from_fen(&position.to_fen()), Ok(position));
+        }
+
+        // Positions reached by moves export what they played into
+        let mut position: Position = Position::new();
+        for (from, to) in [("e2", "e4"), ("c7", "c5"), ("g1", "f3")] {
+            position = position.make_move(Move {
+                from: from.parse().unwrap(),
+                to: to.parse().unwrap(),
+                promotion: None,
+            });
+        }
+        assert_eq!(
+            position.to_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        assert_eq!(Position::from_fen(&position.to_fen()), Ok(position));
+    }
+
+    #[test]
+    fn rejects_invalid_fen() {
+        let cases: [(&str, FenError); 13] = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+                FenError::WrongFieldCount(4),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::WrongRankCount(7),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+                FenError::InvalidPiece('X'),
+            ),
+            (
+                "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::InvalidPiece('9'),
+            ),
+            (
+                "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::WrongRankLength { rank: 7 },
+            ),
+            (
+                "rnbqqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+                FenError::WrongKingCount {
+                    color: PieceColor::Black,
+                    count: 0,
+                },
+            ),
+            (
+                "rnbqkbnP/pppppppp/8/8/8/8/PPPPPPP1/RNBQKBNR w KQq - 0 1",
+                FenError::PawnOnBackRank(Square { x: 7, y: 7 }),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+                FenError::InvalidSideToMove("x".to_string()),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w kqKQ - 0 1",
+                FenError::InvalidCastling("kqKQ".to_string()),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1",
+                FenError::ImpossibleCastling('K'),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e4 0 1",
+                FenError::ImpossibleEnPassant(Square { x: 4, y: 3 }),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0",
+                FenError::InvalidFullmoveNumber("0".to_string()),
+            ),
+            (
+                "rnbqkbnr/ppppp1pp/8/5p1Q/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 0 1",
+                FenError::OpponentInCheck,
+            ),
+        ];
+
+        for (fen, error) in cases {
+            assert_eq!(Position::from_fen(fen), Err(error), "{fen}");
+        }
+    }
+}
@@ -0,0 +1,24 @@
+//! Chess rules without any dependency on the game engine,
+//! used by the Bevy game and usable headless by tools and engines
+
+mod bitboard;
+mod book;
+mod eval;
+mod fen;
+mod movegen;
+mod pgn;
+mod piece;
+mod position;
+mod rules;
+mod san;
+mod search;
+mod syzygy;
+mod tt;
+mod uci;
+mod zobrist;
+
+use crate::zobrist::*;
+pub use crate::{
+    bitboard::*, book::*, eval::*, fen::*, movegen::*, pgn::*, piece::*, position::*, rules::*,
+    san::*, search::*, syzygy::*, tt::*, uci::*,
+};
@@ -0,0 +1,242 @@
+use crate::*;
+
+const RANK_3: Bitboard = 0x0000_0000_00FF_0000;
+const RANK_6: Bitboard = 0x0000_FF00_0000_0000;
+
+impl Position {
+    /// Returns true if the move follows the piece's movement rules,
+    /// it may still leave its own king in check
+    pub fn is_move_valid(&self, from: Square, to: Square) -> bool {
+        self.targets(from) & to.bitboard() != 0
+    }
+
+    /// Returns true if the move follows the piece's movement rules
+    /// and doesn't leave its own king in check
+    pub fn is_move_legal(&self, mv: Move) -> bool {
+        let Some(color) = self.board.piece_at(mv.from).color() else {
+            return false;
+        };
+
+        self.is_move_valid(mv.from, mv.to) && !self.make_move(mv).is_in_check(color)
+    }
+
+    /// Returns the squares the piece on `from` can move to, ignoring checks on its own king
+    fn targets(&self, from: Square) -> Bitboard {
+        let piece_type: PieceType = self.board.piece_at(from);
+        let Some(color) = piece_type.color() else {
+            return 0;
+        };
+
+        let own: Bitboard = self.board.colors[color.index()];
+        let empty: Bitboard = !self.board.occupied();
+
+        match piece_type {
+            PieceType::PawnWhite | PieceType::PawnBlack => {
+                let (single, double): (Bitboard, Bitboard) = match color {
+                    PieceColor::White => {
+                        let single: Bitboard = (from.bitboard() << 8) & empty;
+                        (single, ((single & RANK_3) << 8) & empty)
+                    }
+                    PieceColor::Black => {
+                        let single: Bitboard = (from.bitboard() >> 8) & empty;
+                        (single, ((single & RANK_6) >> 8) & empty)
+                    }
+                };
+
+                // Take piece diagonally, also en passant
+                let en_passant: Bitboard =
+                    self.en_passant_square.map_or(0, |square| square.bitboard());
+                let captures: Bitboard = pawn_attacks(color, from)
+                    & (self.board.colors[color.opposite().index()] | en_passant);
+
+                single | double | captures
+            }
+
+            PieceType::KingWhite | PieceType::KingBlack => {
+                (king_attacks(from) & !own) | self.castling_targets(from, color)
+            }
+
+            _ => self.board.attacks_from(from, piece_type) & !own,
+        }
+    }
+
+    /// Returns the squares the king on `from` can castle to<br />
+    /// King and rook have to be unmoved, the squares between them empty
+    /// and the king can't castle out of, through or into check
+    fn castling_targets(&self, from: Square, color: PieceColor) -> Bitboard {
+        let home_row: u8 = match color {
+            PieceColor::White => 0,
+            PieceColor::Black => 7,
+        };
+
+        if from != (Square { x: 4, y: home_row }) {
+            return 0;
+        }
+
+        let rook_type: PieceType = PieceType::RookWhite.with_color(color);
+        let mut targets: Bitboard = 0;
+
+        // Rook column, the column the king passes through and the king's destination
+        for (side, rook_x, passed_x, to_x) in [
+            (CastlingSide::KingSide, 7, 5, 6),
+            (CastlingSide::QueenSide, 0, 3, 2),
+        ] {
+            let square = |x: u8| Square { x, y: home_row };
+
+            if !self.castling_rights.can_castle(color, side)
+                || self.board.piece_at(square(rook_x)) != rook_type
+            {
+                continue;
+            }
+
+            let between: Bitboard = (rook_x.min(4) + 1..rook_x.max(4))
+                .fold(0, |bitboard, x| bitboard | square(x).bitboard());
+            if between & self.board.occupied() != 0 {
+                continue;
+            }
+
+            if [from, square(passed_x), square(to_x)]
+                .iter()
+                .any(|square| self.board.is_square_attacked(*square, color.opposite()))
+            {
+                continue;
+            }
+
+            targets |= square(to_x).bitboard();
+        }
+
+        targets
+    }
+}
+
+/// Returns all legal moves for the player to move
+pub fn generate_legal_moves(position: &Position) -> Vec<Move> {
+    let mut moves: Vec<Move> = Vec::new();
+
+    for from in squares(position.board.colors[position.color_to_move.index()]) {
+        let piece_type: PieceType = position.board.piece_at(from);
+
+        for to in squares(position.targets(from)) {
+            let promotions: &[PieceType] = match (piece_type, to.y) {
+                (PieceType::PawnWhite, 7) => &[
+                    PieceType::QueenWhite,
+                    PieceType::RookWhite,
+                    PieceType::BishopWhite,
+                    PieceType::KnightWhite,
+                ],
+                (PieceType::PawnBlack, 0) => &[
+                    PieceType::QueenBlack,
+                    PieceType::RookBlack,
+                    PieceType::BishopBlack,
+                    PieceType::KnightBlack,
+                ],
+                _ => &[PieceType::None],
+            };
+
+            let mv: Move = Move {
+                from,
+                to,
+                promotion: None,
+            };
+
+            // Promotion doesn't change whether the own king is left in check
+            if position.make_move(mv).is_in_check(position.color_to_move) {
+                continue;
+            }
+
+            for promotion in promotions {
+                moves.push(Move {
+                    promotion: (*promotion != PieceType::None).then_some(*promotion),
+                    ..mv
+                });
+            }
+        }
+    }
+
+    moves
+}
+
+/// Counts the leaf nodes of the legal move tree to the given depth<br />
+/// https://www.chessprogramming.org/Perft
+pub fn perft(position: &Position, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves: Vec<Move> = generate_legal_moves(position);
+
+    // Leaves don't have to be made
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    moves
+        .iter()
+        .map(|mv| perft(&position.make_move(*mv), depth - 1))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_perft(fen: &str, expected: &[u64]) {
+        let position: Position = Position::from_fen(fen).unwrap();
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(
+                perft(&position, depth as u8 + 1),
+                *nodes,
+                "perft({}) of {fen}",
+                depth + 1
+            );
+        }
+    }
+
+    #[test]
+    fn perft_start_position() {
+        assert_perft(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902, 197281],
+        );
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        assert_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        );
+    }
+
+    #[test]
+    fn perft_position_3() {
+        assert_perft(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238],
+        );
+    }
+
+    #[test]
+    fn perft_position_4() {
+        assert_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        );
+    }
+
+    #[test]
+    fn perft_position_5() {
+        assert_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        );
+    }
+
+    #[test]
+    fn perft_position_6() {
+        assert_perft(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890],
+        );
+    }
+}
@@ -0,0 +1,501 @@
+use crate::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lines of PGN export format are shorter than 80 characters
+const MAX_LINE_LENGTH: usize = 79;
+
+/// A game as written in Portable Game Notation<br />
+/// https://ia902908.us.archive.org/26/items/pgn-standard-1994-03-12/PGN_standard_1994-03-12.txt
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnGame {
+    /// Tags in the order they are written, starting with the Seven Tag Roster
+    pub tags: Vec<(String, String)>,
+    pub start_position: Position,
+    pub moves: Vec<Move>,
+}
+
+impl PgnGame {
+    /// Returns a game without moves with unknown Seven Tag Roster values<br />
+    /// Games not starting from the standard position get `SetUp` and `FEN` tags
+    pub fn new(start_position: Position) -> Self {
+        let mut tags: Vec<(String, String)> = [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", "*"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+        if start_position != Position::new() {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), start_position.to_fen()));
+        }
+
+        Self {
+            tags,
+            start_position,
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of the tag, adding it after the others if it's missing
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag_name, _)| tag_name == name) {
+            Some((_, tag_value)) => *tag_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Returns the game in PGN export format, the `Result` tag is used as the game termination
+    pub fn to_pgn(&self) -> String {
+        let mut pgn: String = String::new();
+
+        for (name, value) in &self.tags {
+            let value: String = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{name} \"{value}\"]\n"));
+        }
+        pgn.push('\n');
+
+        let mut tokens: Vec<String> = Vec::new();
+        let mut position: Position = self.start_position.clone();
+        for (index, mv) in self.moves.iter().enumerate() {
+            match position.color_to_move {
+                PieceColor::White => tokens.push(format!("{}.", position.fullmove_number)),
+                // Black's move needs its number only when it's not right after White's
+                PieceColor::Black if index == 0 => {
+                    tokens.push(format!("{}...", position.fullmove_number))
+                }
+                PieceColor::Black => {}
+            }
+
+            tokens.push(move_to_san(&position, *mv));
+            position = position.make_move(*mv);
+        }
+        tokens.push(self.tag("Result").unwrap_or("*").to_string());
+
+        let mut line_length: usize = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+                pgn.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+
+        pgn
+    }
+}
+
+/// Why a PGN file couldn't be read
+#[derive(Clone, Debug, PartialEq)]
+pub enum PgnError {
+    /// Tag pair that isn't `[Name "value"]`
+    InvalidTag(String),
+    UnterminatedComment,
+    /// Variation opened with `(` that's never closed or closed without being opened
+    UnbalancedVariation,
+    /// `FEN` tag of the game, counted from 1, isn't valid
+    InvalidFen {
+        game: usize,
+        error: FenError,
+    },
+    /// Mainline move of the game, counted from 1, can't be played
+    InvalidMove {
+        game: usize,
+        move_number: u16,
+        error: SanError,
+    },
+}
+
+impl std::fmt::Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag pair [{tag}]"),
+            PgnError::UnterminatedComment => write!(f, "comment is missing its closing brace"),
+            PgnError::UnbalancedVariation => write!(f, "unbalanced parentheses of a variation"),
+            PgnError::InvalidFen { game, error } => write!(f, "game {game}: invalid FEN: {error}"),
+            PgnError::InvalidMove {
+                game,
+                move_number,
+                error,
+            } => write!(f, "game {game}, move {move_number}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+/// Parses all games of a PGN file, keeping their tags and mainline moves<br />
+/// Comments, NAGs, annotation glyphs and variations are skipped
+pub fn parse_pgn(pgn: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games: Vec<PgnGame> = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+    let mut sans: Vec<String> = Vec::new();
+    // Nesting depth of variations, only moves outside of them are the mainline
+    let mut depth: usize = 0;
+
+    let mut chars = pgn.chars().peekable();
+    let mut is_line_start: bool = true;
+    while let Some(c) = chars.next() {
+        let was_line_start: bool = is_line_start;
+        is_line_start = c == '\n';
+
+        match c {
+            // Escaped line and rest-of-line comment
+            '%' | ';' if c == ';' || was_line_start => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        is_line_start = true;
+                        break;
+                    }
+                }
+            }
+            '{' => {
+                if !chars.by_ref().any(|c| c == '}') {
+                    return Err(PgnError::UnterminatedComment);
+                }
+            }
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1).ok_or(PgnError::UnbalancedVariation)?,
+            '[' if depth == 0 => {
+                // Tags after movetext belong to the next game, the previous one had no result
+                if !sans.is_empty() {
+                    games.push(build_game(
+                        std::mem::take(&mut tags),
+                        std::mem::take(&mut sans),
+                        games.len() + 1,
+                    )?);
+                }
+
+                let mut tag: String = String::new();
+                let mut is_quoted: bool = false;
+                let mut is_escaped: bool = false;
+                for c in chars.by_ref() {
+                    if c == ']' && !is_quoted {
+                        break;
+                    }
+                    is_quoted ^= c == '"' && !is_escaped;
+                    is_escaped = c == '\\' && !is_escaped;
+                    tag.push(c);
+                }
+                tags.push(parse_tag(&tag)?);
+            }
+            _ if c.is_whitespace() => {}
+            _ => {
+                let mut token: String = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "[]{}();".contains(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+
+                if depth > 0 {
+                    continue;
+                }
+
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        if !tags.iter().any(|(name, _)| name == "Result") {
+                            tags.push(("Result".to_string(), token));
+                        }
+                        games.push(build_game(
+                            std::mem::take(&mut tags),
+                            std::mem::take(&mut sans),
+                            games.len() + 1,
+                        )?);
+                    }
+                    // Numeric annotation glyph
+                    _ if token.starts_with('$') => {}
+                    _ => {
+                        // Move number may be glued to the move, e.g. `12.e4` or `12...Nf6`
+                        let san: &str = match token.rfind('.') {
+                            Some(index) => &token[index + 1..],
+                            None => &token,
+                        };
+                        if !san.is_empty() && !san.chars().all(|c| c == '!' || c == '?') {
+                            sans.push(san.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if depth > 0 {
+        return Err(PgnError::UnbalancedVariation);
+    }
+
+    if !tags.is_empty() || !sans.is_empty() {
+        games.push(build_game(tags, sans, games.len() + 1)?);
+    }
+
+    Ok(games)
+}
+
+/// Parses the inside of a tag pair, e.g. `Event "F/S Return Match"`
+fn parse_tag(tag: &str) -> Result<(String, String), PgnError> {
+    let invalid = || PgnError::InvalidTag(tag.to_string());
+
+    let (name, value) = tag
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(invalid)?;
+    let value: &str = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+
+    let mut unescaped: String = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        unescaped.push(if c == '\\' {
+            chars.next().ok_or_else(invalid)?
+        } else {
+            c
+        });
+    }
+
+    Ok((name.to_string(), unescaped))
+}
+
+/// Plays the mainline moves from the game's `FEN` tag or the standard starting position
+fn build_game(
+    tags: Vec<(String, String)>,
+    sans: Vec<String>,
+    game: usize,
+) -> Result<PgnGame, PgnError> {
+    let start_position: Position = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => {
+            Position::from_fen(fen).map_err(|error| PgnError::InvalidFen { game, error })?
+        }
+        None => Position::new(),
+    };
+
+    let mut position: Position = start_position.clone();
+    let mut moves: Vec<Move> = Vec::new();
+    for san in sans {
+        let mv: Move = san_to_move(&position, &san).map_err(|error| PgnError::InvalidMove {
+            game,
+            move_number: position.fullmove_number,
+            error,
+        })?;
+        position = position.make_move(mv);
+        moves.push(mv);
+    }
+
+    Ok(PgnGame {
+        tags,
+        start_position,
+        moves,
+    })
+}
+
+/// Returns the PGN result token, `*` if the game isn't over
+pub fn result_token(result: Option<GameResult>) -> &'static str {
+    match result {
+        Some(GameResult::Checkmate {
+            winner: PieceColor::White,
+        }) => "1-0",
+        Some(GameResult::Checkmate {
+            winner: PieceColor::Black,
+        }) => "0-1",
+        Some(_) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+/// Returns the date in the format of the `Date` tag, e.g. `2023.04.30`, in UTC
+pub fn pgn_date(time: SystemTime) -> String {
+    let days: i64 = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() / 86400) as i64;
+
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days: i64 = days + 719468;
+    let era: i64 = days.div_euclid(146097);
+    let day_of_era: i64 = days.rem_euclid(146097);
+    let year_of_era: i64 =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month: i64 = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year: i64 = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn play(position: &Position, moves: &[(&str, &str)]) -> Vec<Move> {
+        let mut position: Position = position.clone();
+        moves
+            .iter()
+            .map(|(from, to)| {
+                let mv: Move = Move {
+                    from: from.parse().unwrap(),
+                    to: to.parse().unwrap(),
+                    promotion: None,
+                };
+                position = position.make_move(mv);
+                mv
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exports_seven_tag_roster_and_movetext() {
+        let mut game: PgnGame = PgnGame::new(Position::new());
+        game.moves = play(
+            &game.start_position,
+            &[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")],
+        );
+        game.set_tag(
+            "Result",
+            result_token(Some(GameResult::Checkmate {
+                winner: PieceColor::Black,
+            })),
+        );
+
+        assert_eq!(
+            game.to_pgn(),
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+             [White \"?\"]\n[Black \"?\"]\n[Result \"0-1\"]\n\n\
+             1. f3 e5 2. g4 Qh4# 0-1\n"
+        );
+    }
+
+    #[test]
+    fn exports_custom_start_position() {
+        let fen: &str = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 20";
+        let mut game: PgnGame = PgnGame::new(Position::from_fen(fen).unwrap());
+        game.moves = play(&game.start_position, &[("e8", "c8"), ("a1", "a8")]);
+
+        assert_eq!(game.tag("SetUp"), Some("1"));
+        assert_eq!(game.tag("FEN"), Some(fen));
+        assert!(game.to_pgn().ends_with("\n\n20... O-O-O 21. Ra8+ *\n"));
+    }
+
+    #[test]
+    fn wraps_long_movetext() {
+        let mut game: PgnGame = PgnGame::new(Position::new());
+        let knight_dance: Vec<(&str, &str)> =
+            [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")].repeat(10);
+        game.moves = play(&game.start_position, &knight_dance);
+
+        let pgn: String = game.to_pgn();
+        assert!(pgn.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert!(pgn.lines().count() > game.tags.len() + 2);
+        assert!(pgn.ends_with(" 20. Ng1 Ng8 *\n"));
+    }
+
+    #[test]
+    fn imports_multiple_games_skipping_annotations() {
+        let pgn: &str = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5
+Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6
+23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5
+hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5
+35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6
+Nf2 42. g4 Bd3 43. Re6 1/2-1/2
+
+[Event "Annotated"]
+[SetUp "1"]
+[FEN "4k3/P7/8/8/8/8/8/4K3 w - - 0 40"]
+
+; rest of line comment
+40.a8=Q+! $1 (40. Kd2 {quiet} (40. Kf2)) 40...Kd7 41. Qb7+ $6 Kd6 *
+"#;
+
+        let games: Vec<PgnGame> = parse_pgn(pgn).unwrap();
+        assert_eq!(games.len(), 2);
+
+        assert_eq!(games[0].tag("White"), Some("Fischer, Robert J."));
+        assert_eq!(games[0].moves.len(), 85);
+        assert!(games[0]
+            .to_pgn()
+            .ends_with(" 41. Ra6 Nf2 42. g4 Bd3 43. Re6 1/2-1/2\n"));
+
+        assert_eq!(games[1].tag("Result"), Some("*"));
+        assert_eq!(games[1].start_position.fullmove_number, 40);
+        assert_eq!(
+            games[1].moves[0],
+            Move {
+                from: "a7".parse().unwrap(),
+                to: "a8".parse().unwrap(),
+                promotion: Some(PieceType::QueenWhite),
+            }
+        );
+        assert_eq!(games[1].moves.len(), 4);
+    }
+
+    #[test]
+    fn rejects_invalid_pgn() {
+        assert_eq!(
+            parse_pgn("[Event \"Unclosed\"]\n\n1. e4 {comment"),
+            Err(PgnError::UnterminatedComment)
+        );
+        assert_eq!(
+            parse_pgn("1. e4 (1. d4 e5 *"),
+            Err(PgnError::UnbalancedVariation)
+        );
+        assert_eq!(
+            parse_pgn("[Event]\n1. e4 *"),
+            Err(PgnError::InvalidTag("Event".to_string()))
+        );
+        assert_eq!(
+            parse_pgn("1. e4 e5 *\n\n1. e4 e5 2. Ke3 *"),
+            Err(PgnError::InvalidMove {
+                game: 2,
+                move_number: 2,
+                error: SanError::Illegal("Ke3".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn formats_date() {
+        assert_eq!(pgn_date(UNIX_EPOCH), "1970.01.01");
+        assert_eq!(
+            pgn_date(UNIX_EPOCH + Duration::from_secs(1_709_208_000)),
+            "2024.02.29"
+        );
+    }
+}
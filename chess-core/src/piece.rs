@@ -0,0 +1,138 @@
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq)]
+pub enum PieceType {
+    PawnBlack,
+    PawnWhite,
+    RookBlack,
+    RookWhite,
+    KnightBlack,
+    KnightWhite,
+    BishopBlack,
+    BishopWhite,
+    QueenBlack,
+    QueenWhite,
+    KingBlack,
+    KingWhite,
+    #[default]
+    None,
+}
+
+impl PieceType {
+    pub fn get_value(&self) -> i8 {
+        match self {
+            PieceType::PawnWhite | PieceType::PawnBlack => 1,
+            PieceType::KnightWhite | PieceType::KnightBlack => 3,
+            PieceType::BishopWhite | PieceType::BishopBlack => 3,
+            PieceType::RookWhite | PieceType::RookBlack => 5,
+            PieceType::QueenWhite | PieceType::QueenBlack => 9,
+            _ => 0,
+        }
+    }
+
+    /// Returns the color of the piece type<br />
+    /// Returns None for PieceType::None
+    pub fn color(&self) -> Option<PieceColor> {
+        match self {
+            PieceType::PawnBlack
+            | PieceType::RookBlack
+            | PieceType::KnightBlack
+            | PieceType::BishopBlack
+            | PieceType::QueenBlack
+            | PieceType::KingBlack => Some(PieceColor::Black),
+            PieceType::PawnWhite
+            | PieceType::RookWhite
+            | PieceType::KnightWhite
+            | PieceType::BishopWhite
+            | PieceType::QueenWhite
+            | PieceType::KingWhite => Some(PieceColor::White),
+            PieceType::None => None,
+        }
+    }
+
+    pub fn is_pawn(&self) -> bool {
+        matches!(self, PieceType::PawnWhite | PieceType::PawnBlack)
+    }
+
+    pub fn is_king(&self) -> bool {
+        matches!(self, PieceType::KingWhite | PieceType::KingBlack)
+    }
+
+    /// Returns the index of the piece's kind in `Board::kinds`, the same for both colors
+    pub fn kind_index(&self) -> usize {
+        match self {
+            PieceType::PawnWhite | PieceType::PawnBlack => 0,
+            PieceType::KnightWhite | PieceType::KnightBlack => 1,
+            PieceType::BishopWhite | PieceType::BishopBlack => 2,
+            PieceType::RookWhite | PieceType::RookBlack => 3,
+            PieceType::QueenWhite | PieceType::QueenBlack => 4,
+            PieceType::KingWhite | PieceType::KingBlack => 5,
+            PieceType::None => unreachable!("PieceType::None is not a valid piece type"),
+        }
+    }
+
+    pub fn from_kind_index(kind: usize, color: PieceColor) -> PieceType {
+        match (kind, color) {
+            (0, PieceColor::White) => PieceType::PawnWhite,
+            (0, PieceColor::Black) => PieceType::PawnBlack,
+            (1, PieceColor::White) => PieceType::KnightWhite,
+            (1, PieceColor::Black) => PieceType::KnightBlack,
+            (2, PieceColor::White) => PieceType::BishopWhite,
+            (2, PieceColor::Black) => PieceType::BishopBlack,
+            (3, PieceColor::White) => PieceType::RookWhite,
+            (3, PieceColor::Black) => PieceType::RookBlack,
+            (4, PieceColor::White) => PieceType::QueenWhite,
+            (4, PieceColor::Black) => PieceType::QueenBlack,
+            (5, PieceColor::White) => PieceType::KingWhite,
+            (5, PieceColor::Black) => PieceType::KingBlack,
+            _ => PieceType::None,
+        }
+    }
+
+    /// Returns the same kind of piece in the given color
+    pub fn with_color(&self, color: PieceColor) -> PieceType {
+        PieceType::from_kind_index(self.kind_index(), color)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq)]
+pub enum PieceColor {
+    White,
+    Black,
+}
+
+impl PieceColor {
+    pub fn opposite(&self) -> PieceColor {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+
+    /// Returns the index of the color in `Board::colors`
+    pub fn index(&self) -> usize {
+        match self {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, Default, PartialEq)]
+pub struct Square {
+    pub x: u8,
+    pub y: u8,
+}
+
+/// Formats the square in algebraic notation, e.g. `e4`
+impl std::fmt::Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}{}", (b'a' + self.x) as char, self.y + 1)
+    }
+}
+
+/// A piece standing on the board
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Piece {
+    pub piece_type: PieceType,
+    pub color: PieceColor,
+    pub square: Square,
+}
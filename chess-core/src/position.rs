@@ -0,0 +1,310 @@
+use crate::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CastlingSide {
+    KingSide,
+    QueenSide,
+}
+
+/// Tracks which castling moves are still available for each side
+#[derive(Clone, Copy, Debug, Hash, PartialEq)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl CastlingRights {
+    pub fn new() -> Self {
+        Self {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+        }
+    }
+
+    pub fn can_castle(&self, color: PieceColor, side: CastlingSide) -> bool {
+        match (color, side) {
+            (PieceColor::White, CastlingSide::KingSide) => self.white_king_side,
+            (PieceColor::White, CastlingSide::QueenSide) => self.white_queen_side,
+            (PieceColor::Black, CastlingSide::KingSide) => self.black_king_side,
+            (PieceColor::Black, CastlingSide::QueenSide) => self.black_queen_side,
+        }
+    }
+
+    /// Revokes castling rights after a move from `from` to `to`<br />
+    /// Any move from or to the starting square of a king or rook
+    /// means that king or rook has moved or has been captured
+    pub fn update(&mut self, from: Square, to: Square) {
+        for square in [from, to] {
+            match (square.x, square.y) {
+                (4, 0) => {
+                    self.white_king_side = false;
+                    self.white_queen_side = false;
+                }
+                (7, 0) => self.white_king_side = false,
+                (0, 0) => self.white_queen_side = false,
+                (4, 7) => {
+                    self.black_king_side = false;
+                    self.black_queen_side = false;
+                }
+                (7, 7) => self.black_king_side = false,
+                (0, 7) => self.black_queen_side = false,
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the castling availability field of FEN
+    pub fn to_fen(self) -> String {
+        let mut fen: String = String::new();
+
+        if self.white_king_side {
+            fen.push('K');
+        }
+        if self.white_queen_side {
+            fen.push('Q');
+        }
+        if self.black_king_side {
+            fen.push('k');
+        }
+        if self.black_queen_side {
+            fen.push('q');
+        }
+
+        if fen.is_empty() {
+            fen.push('-');
+        }
+
+        fen
+    }
+}
+
+impl Default for CastlingRights {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Everything needed to know which moves are legal in a position
+#[derive(Clone, Debug, PartialEq)]
+pub struct Position {
+    pub board: Board,
+    pub color_to_move: PieceColor,
+    pub castling_rights: CastlingRights,
+    pub en_passant_square: Option<Square>,
+    /// Number of halfmoves since the last capture or pawn move, used for the fifty-move rule
+    pub halfmove_clock: u16,
+    /// Starts at 1 and increments after Black's move
+    pub fullmove_number: u16,
+    /// Zobrist hash, updated by `make_move` instead of being computed from scratch
+    pub(crate) hash: u64,
+}
+
+/// A move of the piece standing on `from` to `to`<br />
+/// Castling is the king moving two squares, `promotion` is set only for pawns reaching the last row
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceType>,
+}
+
+impl Position {
+    /// Returns the starting position of a standard game
+    pub fn new() -> Self {
+        let back_row: [(PieceType, PieceType); 8] = [
+            (PieceType::RookWhite, PieceType::RookBlack),
+            (PieceType::KnightWhite, PieceType::KnightBlack),
+            (PieceType::BishopWhite, PieceType::BishopBlack),
+            (PieceType::QueenWhite, PieceType::QueenBlack),
+            (PieceType::KingWhite, PieceType::KingBlack),
+            (PieceType::BishopWhite, PieceType::BishopBlack),
+            (PieceType::KnightWhite, PieceType::KnightBlack),
+            (PieceType::RookWhite, PieceType::RookBlack),
+        ];
+
+        let mut board: Board = Board::default();
+        for (x, (white, black)) in back_row.into_iter().enumerate() {
+            let x: u8 = x as u8;
+            for (piece_type, y) in [
+                (white, 0),
+                (PieceType::PawnWhite, 1),
+                (PieceType::PawnBlack, 6),
+                (black, 7),
+            ] {
+                board.set(Square { x, y }, piece_type);
+            }
+        }
+
+        let mut position: Position = Self {
+            board,
+            color_to_move: PieceColor::White,
+            castling_rights: CastlingRights::new(),
+            en_passant_square: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash: 0,
+        };
+        position.hash = position.compute_hash();
+        position
+    }
+
+    /// Returns the position after the move, the move has to be legal
+    pub fn make_move(&self, mv: Move) -> Position {
+        let piece_type: PieceType = self.board.piece_at(mv.from);
+        assert!(
+            piece_type != PieceType::None,
+            "Move should start on a square with a piece"
+        );
+
+        let is_pawn: bool = piece_type.is_pawn();
+        let is_en_passant: bool = is_pawn && self.en_passant_square == Some(mv.to);
+        let is_capture: bool = self.board.piece_at(mv.to) != PieceType::None || is_en_passant;
+
+        let mut board: Board = self.board;
+        let mut hash: u64 = self.hash
+            ^ castling_key(self.castling_rights)
+            ^ en_passant_key(&self.board, self.color_to_move, self.en_passant_square)
+            ^ turn_key(self.color_to_move);
+
+        let captured_square: Square = if is_en_passant {
+            Square {
+                x: mv.to.x,
+                y: mv.from.y,
+            }
+        } else {
+            mv.to
+        };
+        hash ^= piece_key(board.piece_at(captured_square), captured_square);
+        if is_en_passant {
+            board.remove(captured_square);
+        }
+
+        // Pawns on the last row become queens unless told otherwise
+        let new_type: PieceType = match (mv.promotion, piece_type) {
+            (Some(promotion), _) => promotion,
+            (None, PieceType::PawnWhite) if mv.to.y == 7 => PieceType::QueenWhite,
+            (None, PieceType::PawnBlack) if mv.to.y == 0 => PieceType::QueenBlack,
+            (None, piece_type) => piece_type,
+        };
+        board.remove(mv.from);
+        board.set(mv.to, new_type);
+        hash ^= piece_key(piece_type, mv.from) ^ piece_key(new_type, mv.to);
+
+        // Rook jumps over the king
+        if piece_type.is_king() && (mv.from.x as i8 - mv.to.x as i8).abs() == 2 {
+            let (rook_x, rook_new_x): (u8, u8) = if mv.to.x == 6 { (7, 5) } else { (0, 3) };
+            let rook_square: Square = Square {
+                x: rook_x,
+                y: mv.from.y,
+            };
+            let rook_new_square: Square = Square {
+                x: rook_new_x,
+                y: mv.from.y,
+            };
+            let rook_type: PieceType = board.piece_at(rook_square);
+            board.remove(rook_square);
+            board.set(rook_new_square, rook_type);
+            hash ^= piece_key(rook_type, rook_square) ^ piece_key(rook_type, rook_new_square);
+        }
+
+        let mut castling_rights: CastlingRights = self.castling_rights;
+        castling_rights.update(mv.from, mv.to);
+
+        let en_passant_square: Option<Square> =
+            if is_pawn && (mv.from.y as i8 - mv.to.y as i8).abs() == 2 {
+                Some(Square {
+                    x: mv.from.x,
+                    y: (mv.from.y + mv.to.y) / 2,
+                })
+            } else {
+                None
+            };
+
+        let color_to_move: PieceColor = self.color_to_move.opposite();
+        hash ^= castling_key(castling_rights)
+            ^ en_passant_key(&board, color_to_move, en_passant_square)
+            ^ turn_key(color_to_move);
+
+        Position {
+            board,
+            color_to_move,
+            castling_rights,
+            en_passant_square,
+            halfmove_clock: if is_pawn || is_capture {
+                0
+            } else {
+                self.halfmove_clock + 1
+            },
+            fullmove_number: match self.color_to_move {
+                PieceColor::White => self.fullmove_number,
+                PieceColor::Black => self.fullmove_number + 1,
+            },
+            hash,
+        }
+    }
+
+    /// Returns the position with the other player to move and nothing else changed,
+    /// as if the player to move passed<br />
+    /// Not a legal move, the search uses it to see if the position is good even without moving
+    pub fn make_null_move(&self) -> Position {
+        let color_to_move: PieceColor = self.color_to_move.opposite();
+        let hash: u64 = self.hash
+            ^ en_passant_key(&self.board, self.color_to_move, self.en_passant_square)
+            ^ turn_key(self.color_to_move)
+            ^ turn_key(color_to_move);
+
+        Position {
+            color_to_move,
+            en_passant_square: None,
+            halfmove_clock: self.halfmove_clock + 1,
+            fullmove_number: match self.color_to_move {
+                PieceColor::White => self.fullmove_number,
+                PieceColor::Black => self.fullmove_number + 1,
+            },
+            hash,
+            ..self.clone()
+        }
+    }
+
+    /// Returns all pieces on the board, from a1 to h8
+    pub fn pieces(&self) -> Vec<Piece> {
+        squares(self.board.occupied())
+            .filter_map(|square| self.piece_at(square))
+            .collect()
+    }
+
+    /// Returns the piece standing on the given square
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        let piece_type: PieceType = self.board.piece_at(square);
+        piece_type.color().map(|color| Piece {
+            piece_type,
+            color,
+            square,
+        })
+    }
+
+    /// Returns true if the king of the given color is attacked
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        let king_type: PieceType = PieceType::KingWhite.with_color(color);
+        squares(self.board.pieces_of(king_type))
+            .next()
+            .is_some_and(|king| self.board.is_square_attacked(king, color.opposite()))
+    }
+
+    /// Returns the Zobrist hash identifying the position for repetition detection and the search<br />
+    /// Positions are the same when the same pieces are on the same squares,
+    /// the same player is to move and the same castling and en passant captures are possible
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,82 @@
+use crate::*;
+
+/// How the game ended
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameResult {
+    Checkmate { winner: PieceColor },
+    Stalemate,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+}
+
+/// Returns the result of the game if the player to move has no legal moves<br />
+/// Returns None if the game continues
+pub fn get_game_result(position: &Position) -> Option<GameResult> {
+    if !generate_legal_moves(position).is_empty() {
+        return None;
+    }
+
+    if position.is_in_check(position.color_to_move) {
+        Some(GameResult::Checkmate {
+            winner: position.color_to_move.opposite(),
+        })
+    } else {
+        Some(GameResult::Stalemate)
+    }
+}
+
+/// Returns the draw the game ended in, if any<br />
+/// `position_history` holds hashes of all positions of the game, the current one last<br />
+/// Checkmate and stalemate are handled by `get_game_result`
+pub fn get_draw_result(position: &Position, position_history: &[u64]) -> Option<GameResult> {
+    if is_material_insufficient(&position.pieces()) {
+        return Some(GameResult::InsufficientMaterial);
+    }
+
+    // Current position is the last one in the history
+    if let Some(current) = position_history.last() {
+        if position_history
+            .iter()
+            .filter(|hash| *hash == current)
+            .count()
+            >= 3
+        {
+            return Some(GameResult::ThreefoldRepetition);
+        }
+    }
+
+    // Fifty moves by each player
+    if position.halfmove_clock >= 100 {
+        return Some(GameResult::FiftyMoveRule);
+    }
+
+    None
+}
+
+/// Returns true if neither player can checkmate<br />
+/// That's king against king with at most one minor piece,
+/// or kings and bishops that are all on squares of the same color
+pub fn is_material_insufficient(pieces: &[Piece]) -> bool {
+    let others: Vec<&Piece> = pieces
+        .iter()
+        .filter(|piece| !piece.piece_type.is_king())
+        .collect();
+
+    match others.as_slice() {
+        [] => true,
+        [piece] => matches!(
+            piece.piece_type,
+            PieceType::KnightWhite
+                | PieceType::KnightBlack
+                | PieceType::BishopWhite
+                | PieceType::BishopBlack
+        ),
+        [first, ..] => others.iter().all(|piece| {
+            matches!(
+                piece.piece_type,
+                PieceType::BishopWhite | PieceType::BishopBlack
+            ) && (piece.square.x + piece.square.y) % 2 == (first.square.x + first.square.y) % 2
+        }),
+    }
+}
@@ -0,0 +1,487 @@
+use crate::*;
+
+/// Returns the letter of the piece in SAN, pawns have none
+fn piece_letter(piece_type: PieceType) -> Option<char> {
+    match piece_type {
+        PieceType::KnightWhite | PieceType::KnightBlack => Some('N'),
+        PieceType::BishopWhite | PieceType::BishopBlack => Some('B'),
+        PieceType::RookWhite | PieceType::RookBlack => Some('R'),
+        PieceType::QueenWhite | PieceType::QueenBlack => Some('Q'),
+        PieceType::KingWhite | PieceType::KingBlack => Some('K'),
+        _ => None,
+    }
+}
+
+/// Returns the move in Standard Algebraic Notation, e.g. `Nbd7`, `exd6`, `O-O` or `e8=Q#`<br />
+/// The move has to be legal in the position
+pub fn move_to_san(position: &Position, mv: Move) -> String {
+    let piece_type: PieceType = position.board.piece_at(mv.from);
+    let mut san: String = String::new();
+
+    if piece_type.is_king() && (mv.from.x as i8 - mv.to.x as i8).abs() == 2 {
+        san.push_str(if mv.to.x == 6 { "O-O" } else { "O-O-O" });
+    } else {
+        let is_capture: bool = position.board.piece_at(mv.to) != PieceType::None
+            || (piece_type.is_pawn() && position.en_passant_square == Some(mv.to));
+
+        match piece_letter(piece_type) {
+            Some(letter) => {
+                san.push(letter);
+
+                // Other pieces of the same type that could move to the same square
+                let others: Vec<Square> = generate_legal_moves(position)
+                    .into_iter()
+                    .filter(|other| {
+                        other.to == mv.to
+                            && other.from != mv.from
+                            && position.board.piece_at(other.from) == piece_type
+                    })
+                    .map(|other| other.from)
+                    .collect();
+
+                if !others.is_empty() {
+                    let file: char = (b'a' + mv.from.x) as char;
+                    let rank: char = (b'1' + mv.from.y) as char;
+                    if others.iter().all(|other| other.x != mv.from.x) {
+                        san.push(file);
+                    } else if others.iter().all(|other| other.y != mv.from.y) {
+                        san.push(rank);
+                    } else {
+                        san.push(file);
+                        san.push(rank);
+                    }
+                }
+            }
+            // Pawn captures are named by the file they start from
+            None if is_capture => san.push((b'a' + mv.from.x) as char),
+            None => {}
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&mv.to.to_string());
+
+        if let Some(letter) = mv.promotion.and_then(piece_letter) {
+            san.push('=');
+            san.push(letter);
+        }
+    }
+
+    let after: Position = position.make_move(mv);
+    if after.is_in_check(after.color_to_move) {
+        san.push(if generate_legal_moves(&after).is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+
+    san
+}
+
+/// Why a SAN string couldn't be resolved to a move
+#[derive(Clone, Debug, PartialEq)]
+pub enum SanError {
+    /// Not a move in SAN at all
+    Invalid(String),
+    /// No legal move matches
+    Illegal(String),
+    /// More than one legal move matches, the SAN lacks disambiguation
+    Ambiguous(String),
+}
+
+impl std::fmt::Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SanError::Invalid(san) => write!(f, "'{san}' is not a move in SAN"),
+            SanError::Illegal(san) => write!(f, "'{san}' is not a legal move"),
+            SanError::Ambiguous(san) => write!(f, "'{san}' matches more than one legal move"),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+/// Resolves a move in Standard Algebraic Notation against the position, e.g. `Nbd7` or `exd6`<br />
+/// Check and annotation suffixes like `+`, `#` or `!?` are ignored, castling may be written with zeros
+pub fn san_to_move(position: &Position, san: &str) -> Result<Move, SanError> {
+    let invalid = || SanError::Invalid(san.to_string());
+    let trimmed: &str = san.trim_end_matches(['+', '#', '!', '?']);
+
+    let legal_moves: Vec<Move> = generate_legal_moves(position);
+    let is_king_move = |mv: &Move| position.board.piece_at(mv.from).is_king() && mv.from.x == 4;
+
+    let candidates: Vec<Move> = match trimmed {
+        "O-O" | "0-0" => legal_moves
+            .into_iter()
+            .filter(|mv| is_king_move(mv) && mv.to.x == 6)
+            .collect(),
+        "O-O-O" | "0-0-0" => legal_moves
+            .into_iter()
+            .filter(|mv| is_king_move(mv) && mv.to.x == 2)
+            .collect(),
+        _ => {
+            let mut chars: Vec<char> = trimmed.chars().collect();
+
+            // Promotion is `e8=Q`, some tools leave out the `=`
+            let mut promotion: Option<PieceType> = None;
+            if let Some(piece_type) = chars
+                .last()
+                .filter(|c| "NBRQ".contains(**c))
+                .and_then(|c| piece_type_from_fen(*c))
+            {
+                promotion = Some(piece_type.with_color(position.color_to_move));
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+
+            let piece_type: PieceType = match chars.first() {
+                Some(&c) if "NBRQK".contains(c) => {
+                    chars.remove(0);
+                    piece_type_from_fen(c)
+                        .ok_or_else(invalid)?
+                        .with_color(position.color_to_move)
+                }
+                _ => PieceType::PawnWhite.with_color(position.color_to_move),
+            };
+
+            chars.retain(|c| *c != 'x');
+            if chars.len() < 2 {
+                return Err(invalid());
+            }
+            let to: Square = chars
+                .split_off(chars.len() - 2)
+                .iter()
+                .collect::<String>()
+                .parse()
+                .map_err(|_| invalid())?;
+
+            // Whatever is left tells the file and/or rank the piece comes from
+            let mut from_x: Option<u8> = None;
+            let mut from_y: Option<u8> = None;
+            for c in chars {
+                match c {
+                    'a'..='h' if from_x.is_none() => from_x = Some(c as u8 - b'a'),
+                    '1'..='8' if from_y.is_none() => from_y = Some(c as u8 - b'1'),
+                    _ => return Err(invalid()),
+                }
+            }
+
+            legal_moves
+                .into_iter()
+                .filter(|mv| {
+                    mv.to == to
+                        && position.board.piece_at(mv.from) == piece_type
+                        && from_x.is_none_or(|x| mv.from.x == x)
+                        && from_y.is_none_or(|y| mv.from.y == y)
+                        && mv.promotion == promotion
+                })
+                .collect()
+        }
+    };
+
+    match candidates[..] {
+        [mv] => Ok(mv),
+        [] => Err(SanError::Illegal(san.to_string())),
+        _ => Err(SanError::Ambiguous(san.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(fen: &str, from: &str, to: &str, promotion: Option<PieceType>) -> String {
+        let position: Position = Position::from_fen(fen).unwrap();
+        move_to_san(
+            &position,
+            Move {
+                from: from.parse().unwrap(),
+                to: to.parse().unwrap(),
+                promotion,
+            },
+        )
+    }
+
+    #[test]
+    fn renders_san() {
+        let knights: &str = "1n2k3/8/5n2/8/8/1n6/8/1n2K3 b - - 0 1";
+        assert_eq!(san(knights, "b8", "d7", None), "Nbd7");
+        assert_eq!(san(knights, "b3", "d2", None), "N3d2");
+        assert_eq!(san(knights, "f6", "e4", None), "Ne4");
+
+        let queens: &str = "8/8/8/7k/8/Q7/8/Q1Q4K w - - 0 1";
+        assert_eq!(san(queens, "a1", "b2", None), "Qa1b2");
+
+        let en_passant: &str = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2";
+        assert_eq!(san(en_passant, "e5", "d6", None), "exd6");
+        assert_eq!(san(en_passant, "e5", "e6", None), "e6");
+
+        let promotion: &str = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(
+            san(promotion, "a7", "a8", Some(PieceType::QueenWhite)),
+            "a8=Q+"
+        );
+        assert_eq!(
+            san(promotion, "a7", "a8", Some(PieceType::KnightWhite)),
+            "a8=N"
+        );
+
+        let castling: &str = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(castling, "e1", "g1", None), "O-O");
+        assert_eq!(san(castling, "e1", "c1", None), "O-O-O");
+        assert_eq!(san(castling, "a1", "a8", None), "Rxa8+");
+
+        let fools_mate: &str = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2";
+        assert_eq!(san(fools_mate, "d8", "h4", None), "Qh4#");
+    }
+
+    #[test]
+    fn disambiguates_by_file_rank_or_both() {
+        let rooks_on_rank: &str = "4k3/8/8/8/8/4K3/8/R6R w - - 0 1";
+        assert_eq!(san(rooks_on_rank, "a1", "d1", None), "Rad1");
+        assert_eq!(san(rooks_on_rank, "h1", "d1", None), "Rhd1");
+
+        let rooks_on_file: &str = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(rooks_on_file, "a1", "a3", None), "R1a3");
+        assert_eq!(san(rooks_on_file, "a5", "a3", None), "R5a3");
+        // Only one rook reaches b1
+        assert_eq!(san(rooks_on_file, "a1", "b1", None), "Rb1");
+
+        let queens: &str = "8/8/8/7k/8/Q7/8/Q1Q4K w - - 0 1";
+        assert_eq!(san(queens, "a1", "b2", None), "Qa1b2");
+        assert_eq!(san(queens, "c1", "b2", None), "Qcb2");
+        assert_eq!(san(queens, "a3", "b2", None), "Q3b2");
+
+        // Pawn captures only ever name the file
+        let pawns: &str = "4k3/8/8/3p4/2P1P3/8/8/4K3 w - - 0 1";
+        assert_eq!(san(pawns, "c4", "d5", None), "cxd5");
+        assert_eq!(san(pawns, "e4", "d5", None), "exd5");
+    }
+
+    #[test]
+    fn renders_check_and_mate_suffixes() {
+        let discovered_check: &str = "4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1";
+        assert_eq!(san(discovered_check, "e2", "c3", None), "Nc3+");
+
+        let back_rank: &str = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        assert_eq!(san(back_rank, "a1", "a8", None), "Ra8#");
+        assert_eq!(san(back_rank, "a1", "a7", None), "Ra7");
+
+        // Stalemate is neither
+        let stalemate: &str = "k7/8/1Q6/8/8/8/8/7K w - - 0 1";
+        assert_eq!(san(stalemate, "b6", "c7", None), "Qc7");
+    }
+
+    #[test]
+    fn renders_promotions_and_castling() {
+        let capture: &str = "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(
+            san(capture, "a7", "b8", Some(PieceType::QueenWhite)),
+            "axb8=Q+"
+        );
+        assert_eq!(
+            san(capture, "a7", "b8", Some(PieceType::RookWhite)),
+            "axb8=R+"
+        );
+        assert_eq!(
+            san(capture, "a7", "b8", Some(PieceType::BishopWhite)),
+            "axb8=B"
+        );
+
+        let black: &str = "4k3/8/8/8/8/8/p7/4K3 b - - 0 1";
+        assert_eq!(san(black, "a2", "a1", Some(PieceType::QueenBlack)), "a1=Q+");
+
+        let castling: &str = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1";
+        assert_eq!(san(castling, "e8", "g8", None), "O-O");
+        assert_eq!(san(castling, "e8", "c8", None), "O-O-O");
+
+        // Rook gives check once castled
+        let castling_check: &str = "5k2/8/8/8/8/8/8/4K2R w K - 0 1";
+        assert_eq!(san(castling_check, "e1", "g1", None), "O-O+");
+    }
+
+    #[test]
+    fn parses_san() {
+        let position: Position =
+            Position::from_fen("1n2k3/8/5n2/8/8/1n6/8/1n2K3 b - - 0 1").unwrap();
+        assert_eq!(
+            san_to_move(&position, "Nbd7"),
+            Ok(Move {
+                from: "b8".parse().unwrap(),
+                to: "d7".parse().unwrap(),
+                promotion: None,
+            })
+        );
+        assert_eq!(
+            san_to_move(&position, "Nd7"),
+            Err(SanError::Ambiguous("Nd7".to_string()))
+        );
+        assert_eq!(
+            san_to_move(&position, "Nh8"),
+            Err(SanError::Illegal("Nh8".to_string()))
+        );
+        assert_eq!(
+            san_to_move(&position, "Nz9"),
+            Err(SanError::Invalid("Nz9".to_string()))
+        );
+
+        let position: Position = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        assert_eq!(
+            san_to_move(&position, "exd6"),
+            Ok(Move {
+                from: "e5".parse().unwrap(),
+                to: "d6".parse().unwrap(),
+                promotion: None,
+            })
+        );
+
+        let position: Position = Position::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        for san in ["a8=Q+", "a8Q", "a8=Q!?"] {
+            assert_eq!(
+                san_to_move(&position, san).map(|mv| mv.promotion),
+                Ok(Some(PieceType::QueenWhite))
+            );
+        }
+        assert_eq!(
+            san_to_move(&position, "a8"),
+            Err(SanError::Illegal("a8".to_string()))
+        );
+
+        let position: Position =
+            Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        assert_eq!(
+            san_to_move(&position, "0-0-0").map(|mv| mv.to),
+            Ok("c8".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_ambiguous_san() {
+        let position: Position = Position::from_fen("4k3/8/8/R7/8/4K3/8/R6R w - - 0 1").unwrap();
+        for ambiguous in ["Ra3", "Rd1", "Rxd1"] {
+            assert_eq!(
+                san_to_move(&position, ambiguous),
+                Err(SanError::Ambiguous(ambiguous.to_string()))
+            );
+        }
+        assert_eq!(
+            san_to_move(&position, "R1a3").map(|mv| mv.from),
+            Ok("a1".parse().unwrap())
+        );
+        assert_eq!(
+            san_to_move(&position, "Rhd1").map(|mv| mv.from),
+            Ok("h1".parse().unwrap())
+        );
+        // File alone doesn't tell the rooks on the a-file apart, file and rank do
+        assert_eq!(
+            san_to_move(&position, "Raa3"),
+            Err(SanError::Ambiguous("Raa3".to_string()))
+        );
+        assert_eq!(
+            san_to_move(&position, "Ra5a3").map(|mv| mv.from),
+            Ok("a5".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn parses_castling_with_letters_or_zeros() {
+        let position: Position =
+            Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        for (san, to) in [
+            ("O-O", "g1"),
+            ("0-0", "g1"),
+            ("O-O-O", "c1"),
+            ("0-0-0", "c1"),
+        ] {
+            assert_eq!(
+                san_to_move(&position, san).map(|mv| (mv.from, mv.to)),
+                Ok(("e1".parse().unwrap(), to.parse().unwrap())),
+                "{san}"
+            );
+        }
+
+        // No castling without the right
+        let position: Position = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1").unwrap();
+        assert_eq!(
+            san_to_move(&position, "O-O"),
+            Err(SanError::Illegal("O-O".to_string()))
+        );
+        assert_eq!(
+            san_to_move(&position, "0-0-0").map(|mv| mv.to),
+            Ok("c1".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn parses_promotions_with_or_without_equals() {
+        let position: Position = Position::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        for (san, promotion) in [
+            ("axb8=Q+", PieceType::QueenWhite),
+            ("axb8Q", PieceType::QueenWhite),
+            ("axb8=N", PieceType::KnightWhite),
+            ("axb8N", PieceType::KnightWhite),
+            ("a8=R", PieceType::RookWhite),
+            ("a8B", PieceType::BishopWhite),
+        ] {
+            assert_eq!(
+                san_to_move(&position, san).map(|mv| mv.promotion),
+                Ok(Some(promotion)),
+                "{san}"
+            );
+        }
+
+        let position: Position = Position::from_fen("4k3/8/8/8/8/8/p7/4K3 b - - 0 1").unwrap();
+        assert_eq!(
+            san_to_move(&position, "a1=Q+").map(|mv| mv.promotion),
+            Ok(Some(PieceType::QueenBlack))
+        );
+    }
+
+    #[test]
+    fn ignores_annotations() {
+        let position: Position = Position::new();
+        for san in ["Nf3!", "Nf3?", "Nf3!?", "Nf3?!", "Nf3!!", "Nf3??"] {
+            assert_eq!(
+                san_to_move(&position, san).map(|mv| mv.to_uci()),
+                Ok("g1f3".to_string()),
+                "{san}"
+            );
+        }
+
+        let position: Position =
+            Position::from_fen("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2")
+                .unwrap();
+        for san in ["Qh4#", "Qh4#!", "Qh4+!?", "Qh4"] {
+            assert_eq!(
+                san_to_move(&position, san).map(|mv| mv.to_uci()),
+                Ok("d8h4".to_string()),
+                "{san}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_rendered_san_back() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let position: Position = Position::from_fen(fen).unwrap();
+            for mv in generate_legal_moves(&position) {
+                let after: Position = position.make_move(mv);
+                for reply in generate_legal_moves(&after) {
+                    assert_eq!(
+                        san_to_move(&after, &move_to_san(&after, reply)),
+                        Ok(reply),
+                        "{fen}"
+                    );
+                }
+            }
+        }
+    }
+}
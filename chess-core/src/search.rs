@@ -0,0 +1,1096 @@
+use crate::*;
+use std::{
+    cmp::Reverse,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread::ScopedJoinHandle,
+    time::{Duration, Instant},
+};
+
+/// Score of being checkmated at the root, mates found deeper score closer to zero
+pub const MATE_SCORE: i32 = 30_000;
+
+/// Scores this far from [`MATE_SCORE`] are mates, the distance is the number of plies
+const MATE_THRESHOLD: i32 = MATE_SCORE - 1_000;
+
+/// Score of a tablebase win at the root, below mate scores so it isn't shown as a mate
+pub const TB_WIN_SCORE: i32 = MATE_THRESHOLD - 1_000;
+
+/// Nodes searched between checks of the time limit
+const TIME_CHECK_INTERVAL: u64 = 1_024;
+
+/// Half the width of the first aspiration window around the last iteration's score
+const ASPIRATION_WINDOW: i32 = PAWN_VALUE / 2;
+
+/// Checks aren't extended past this ply, so long series of checks can't blow up the search
+const MAX_EXTENDED_PLY: i32 = 64;
+
+/// Move ordering keys of [`Searcher::move_order_key`], history scores stay below killer moves
+const TT_MOVE_ORDER: i32 = i32::MAX;
+const CAPTURE_ORDER: i32 = 1_000_000;
+const KILLER_ORDER: i32 = 900_000;
+
+/// Search enhancements, each can be turned off to measure what it brings<br />
+/// https://www.chessprogramming.org/Search
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchFeatures {
+    /// Captures of the most valuable victim by the least valuable attacker are searched first
+    pub mvv_lva: bool,
+    /// Quiet moves that failed high at the same ply are searched right after captures
+    pub killer_moves: bool,
+    /// Other quiet moves are ordered by how deep they failed high anywhere in the search
+    pub history_heuristic: bool,
+    /// Positions still failing high after passing the move are cut off with a shallower search
+    pub null_move_pruning: bool,
+    /// Late quiet moves are searched shallower first
+    pub late_move_reductions: bool,
+    /// Iterations search a narrow window around the last iteration's score
+    pub aspiration_windows: bool,
+    /// Moves giving check are searched a ply deeper
+    pub check_extensions: bool,
+}
+
+impl SearchFeatures {
+    /// Plain alpha-beta with only the transposition table's move ordering
+    pub const NONE: SearchFeatures = SearchFeatures {
+        mvv_lva: false,
+        killer_moves: false,
+        history_heuristic: false,
+        null_move_pruning: false,
+        late_move_reductions: false,
+        aspiration_windows: false,
+        check_extensions: false,
+    };
+
+    /// Names of the features as UCI options
+    const OPTION_NAMES: [&'static str; 7] = [
+        "MVVLVA",
+        "KillerMoves",
+        "HistoryHeuristic",
+        "NullMovePruning",
+        "LateMoveReductions",
+        "AspirationWindows",
+        "CheckExtensions",
+    ];
+
+    fn flags_mut(&mut self) -> [&mut bool; 7] {
+        [
+            &mut self.mvv_lva,
+            &mut self.killer_moves,
+            &mut self.history_heuristic,
+            &mut self.null_move_pruning,
+            &mut self.late_move_reductions,
+            &mut self.aspiration_windows,
+            &mut self.check_extensions,
+        ]
+    }
+
+    /// Returns the UCI option name of each feature with whether it's on
+    pub fn options(&self) -> [(&'static str, bool); 7] {
+        let mut features: SearchFeatures = *self;
+        let flags: [bool; 7] = features.flags_mut().map(|flag| *flag);
+        std::array::from_fn(|index| (Self::OPTION_NAMES[index], flags[index]))
+    }
+
+    /// Returns the feature of the UCI option name, ignoring case
+    pub fn option_mut(&mut self, name: &str) -> Option<&mut bool> {
+        let index: usize = Self::OPTION_NAMES
+            .iter()
+            .position(|option_name| option_name.eq_ignore_ascii_case(name))?;
+        self.flags_mut().into_iter().nth(index)
+    }
+}
+
+impl Default for SearchFeatures {
+    fn default() -> Self {
+        Self {
+            mvv_lva: true,
+            killer_moves: true,
+            history_heuristic: true,
+            null_move_pruning: true,
+            late_move_reductions: true,
+            aspiration_windows: true,
+            check_extensions: true,
+        }
+    }
+}
+
+/// How long and how deep to search
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchLimits {
+    /// Deepest iteration in plies, quiescence search goes further
+    pub depth: u8,
+    /// Search stops after this long, the best move of the last finished iteration is kept
+    pub time: Option<Duration>,
+    /// Largest random bonus or malus added to the score of each root move, in centipawns<br />
+    /// Weakens the choice of move, 0 always plays the best move found
+    pub noise: i32,
+    /// Seed of the noise, the same seed picks the same move
+    pub seed: u64,
+    /// Threads searching at the same time, sharing the transposition table<br />
+    /// https://www.chessprogramming.org/Lazy_SMP
+    pub threads: usize,
+    pub features: SearchFeatures,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            depth: 4,
+            time: Some(Duration::from_secs(1)),
+            noise: 0,
+            seed: 0,
+            threads: 1,
+            features: SearchFeatures::default(),
+        }
+    }
+}
+
+/// Best move found by [`search`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchResult {
+    /// None if the player to move has no legal move
+    pub best_move: Option<Move>,
+    /// Score of the best move from the side to move's point of view, in centipawns
+    pub score: i32,
+    /// Depth of the last finished iteration
+    pub depth: u8,
+    /// Number of positions searched by all threads, quiescence included
+    pub nodes: u64,
+    /// How long the search took
+    pub time: Duration,
+}
+
+impl SearchResult {
+    /// Returns the number of moves to mate, negative if the side to move gets mated
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score.abs() < MATE_THRESHOLD {
+            return None;
+        }
+
+        let plies: i32 = MATE_SCORE - self.score.abs();
+        Some(self.score.signum() * (plies + 1) / 2)
+    }
+
+    pub fn nodes_per_second(&self) -> u64 {
+        (self.nodes as f64 / self.time.as_secs_f64().max(0.001)) as u64
+    }
+}
+
+/// State shared by all nodes of one thread's search
+struct Searcher<'a> {
+    nodes: u64,
+    /// Nodes of all threads, each adds its own now and then
+    all_nodes: &'a AtomicU64,
+    /// Nodes of this thread already added to `all_nodes`
+    counted_nodes: u64,
+    started_at: Instant,
+    deadline: Option<Instant>,
+    /// Set from another thread to cancel the search
+    stop: &'a AtomicBool,
+    /// Set once the main thread finished, helper threads then stop too
+    finished: &'a AtomicBool,
+    stopped: bool,
+    tt: &'a TranspositionTable,
+    /// Hashes of the game's positions and of the positions on the way to the current node
+    history: Vec<u64>,
+    tablebase: Option<&'a Tablebase>,
+    features: SearchFeatures,
+    /// Two quiet moves that last failed high at each ply
+    killers: Vec<[Option<Move>; 2]>,
+    /// Score of quiet moves by from and to square, higher for moves that failed high more and deeper
+    history_scores: Box<[[i32; 64]; 64]>,
+}
+
+/// Stores mate scores relative to the position, so they stay right when reached at another ply
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score + ply
+    } else if score <= -MATE_THRESHOLD {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score - ply
+    } else if score <= -MATE_THRESHOLD {
+        score + ply
+    } else {
+        score
+    }
+}
+
+impl Searcher<'_> {
+    /// Returns true once the time is up or the search is cancelled,
+    /// the running iteration is then thrown away
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(TIME_CHECK_INTERVAL) {
+            self.count_nodes();
+            self.stopped = self.stop.load(Ordering::Relaxed)
+                || self.finished.load(Ordering::Relaxed)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.stopped
+    }
+
+    /// Adds the nodes searched since the last call to the nodes of all threads, returns their sum
+    fn count_nodes(&mut self) -> u64 {
+        let new_nodes: u64 = self.nodes - self.counted_nodes;
+        self.counted_nodes = self.nodes;
+        self.all_nodes.fetch_add(new_nodes, Ordering::Relaxed) + new_nodes
+    }
+
+    /// Alpha-beta negamax, https://www.chessprogramming.org/Alpha-Beta<br />
+    /// `allow_null_move` is false right after a null move, so two never follow each other
+    fn alpha_beta(
+        &mut self,
+        position: &Position,
+        depth: u8,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+        allow_null_move: bool,
+    ) -> i32 {
+        if depth == 0 {
+            return self.quiescence(position, alpha, beta);
+        }
+
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        // Repeating a position since the last capture or pawn move is a draw,
+        // once is enough as the side that could avoid it would have done so
+        let hash: u64 = position.hash();
+        let reversible: usize = (position.halfmove_clock as usize).min(self.history.len());
+        if self.history[self.history.len() - reversible..].contains(&hash)
+            || position.halfmove_clock >= 100
+        {
+            return 0;
+        }
+
+        // Tablebase result is exact once a capture or pawn move reset the fifty-move counter
+        if let Some(wdl) = self
+            .tablebase
+            .filter(|_| position.halfmove_clock == 0)
+            .and_then(|tablebase| tablebase.probe_wdl(position))
+        {
+            return match wdl {
+                Wdl::Win => TB_WIN_SCORE - ply,
+                Wdl::Loss => -TB_WIN_SCORE + ply,
+                Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => 0,
+            };
+        }
+
+        let tt_entry: Option<TtEntry> = self.tt.probe(hash);
+        if let Some(entry) = tt_entry.filter(|entry| entry.depth >= depth) {
+            let score: i32 = score_from_tt(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
+            }
+        }
+
+        let mut moves: Vec<Move> = generate_legal_moves(position);
+        let in_check: bool = position.is_in_check(position.color_to_move);
+        if moves.is_empty() {
+            return if in_check { -MATE_SCORE + ply } else { 0 };
+        }
+
+        // Opponent moving twice and still not getting below beta means a real move won't either<br />
+        // Passing is illegal in check, and often the best move without pieces because of zugzwang
+        if self.features.null_move_pruning
+            && allow_null_move
+            && depth >= 3
+            && !in_check
+            && beta.abs() < MATE_THRESHOLD
+            && has_pieces(position, position.color_to_move)
+        {
+            let reduction: u8 = if depth >= 6 { 3 } else { 2 };
+            // Positions before the null move aren't repetitions of the ones after it
+            let mut null_position: Position = position.make_null_move();
+            null_position.halfmove_clock = 0;
+
+            self.history.push(hash);
+            let score: i32 = -self.alpha_beta(
+                &null_position,
+                depth - 1 - reduction,
+                ply + 1,
+                -beta,
+                -beta + 1,
+                false,
+            );
+            self.history.pop();
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+        }
+
+        let tt_move: Option<Move> = tt_entry.and_then(|entry| entry.best_move);
+        moves.sort_by_cached_key(|mv| Reverse(self.move_order_key(position, *mv, tt_move, ply)));
+
+        let original_alpha: i32 = alpha;
+        let mut best_move: Option<Move> = None;
+        self.history.push(hash);
+        for (index, mv) in moves.into_iter().enumerate() {
+            let child: Position = position.make_move(mv);
+            let gives_check: bool = child.is_in_check(child.color_to_move);
+            let quiet: bool = !is_capture(position, mv) && mv.promotion.is_none();
+
+            // Checks are searched deeper, the next moves are forced
+            let new_depth: u8 =
+                if self.features.check_extensions && gives_check && ply < MAX_EXTENDED_PLY {
+                    depth
+                } else {
+                    depth - 1
+                };
+
+            // Late quiet moves are rarely the best, a shallower search has to show they raise alpha
+            let reduction: u8 = if self.features.late_move_reductions
+                && depth >= 3
+                && quiet
+                && !in_check
+                && !gives_check
+            {
+                match index {
+                    0..=2 => 0,
+                    3..=5 => 1,
+                    _ => 2,
+                }
+            } else {
+                0
+            };
+            let reduced_fails_low: bool = reduction > 0
+                && -self.alpha_beta(
+                    &child,
+                    new_depth - reduction,
+                    ply + 1,
+                    -alpha - 1,
+                    -alpha,
+                    true,
+                ) <= alpha;
+            let score: i32 = if reduced_fails_low {
+                alpha
+            } else {
+                -self.alpha_beta(&child, new_depth, ply + 1, -beta, -alpha, true)
+            };
+            if self.stopped {
+                self.history.pop();
+                return 0;
+            }
+
+            if score >= beta {
+                self.history.pop();
+                if quiet {
+                    self.record_cutoff(mv, depth, ply);
+                }
+                self.tt.store(TtEntry {
+                    hash,
+                    best_move: Some(mv),
+                    score: score_to_tt(beta, ply),
+                    depth,
+                    bound: Bound::Lower,
+                });
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+            }
+        }
+        self.history.pop();
+
+        self.tt.store(TtEntry {
+            hash,
+            best_move,
+            score: score_to_tt(alpha, ply),
+            depth,
+            bound: if alpha > original_alpha {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            },
+        });
+        alpha
+    }
+
+    /// Searches captures and promotions until the position is quiet,
+    /// so the evaluation doesn't stop in the middle of an exchange<br />
+    /// https://www.chessprogramming.org/Quiescence_Search
+    fn quiescence(&mut self, position: &Position, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        // Side to move doesn't have to capture, the evaluation is the lower bound
+        let stand_pat: i32 = evaluate(position);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = generate_legal_moves(position)
+            .into_iter()
+            .filter(|mv| is_capture(position, *mv) || mv.promotion.is_some())
+            .collect();
+        if self.features.mvv_lva {
+            moves.sort_by_cached_key(|mv| Reverse(mvv_lva(position, *mv)));
+        }
+
+        for mv in moves {
+            let score: i32 = -self.quiescence(&position.make_move(mv), -beta, -alpha);
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+
+    /// Returns how early to search the move, higher first: the transposition table's move,
+    /// captures, killer moves, then the other quiet moves by their history<br />
+    /// Moves of the same key keep the order they were generated in
+    fn move_order_key(
+        &self,
+        position: &Position,
+        mv: Move,
+        tt_move: Option<Move>,
+        ply: i32,
+    ) -> i32 {
+        if tt_move == Some(mv) {
+            return TT_MOVE_ORDER;
+        }
+        if is_capture(position, mv) || mv.promotion.is_some() {
+            return if self.features.mvv_lva {
+                CAPTURE_ORDER + mvv_lva(position, mv)
+            } else {
+                0
+            };
+        }
+
+        if self.features.killer_moves {
+            let killers: [Option<Move>; 2] =
+                self.killers.get(ply as usize).copied().unwrap_or_default();
+            if let Some(index) = killers.iter().position(|killer| *killer == Some(mv)) {
+                return KILLER_ORDER - index as i32;
+            }
+        }
+        if self.features.history_heuristic {
+            return self.history_scores[mv.from.index()][mv.to.index()].min(KILLER_ORDER - 2);
+        }
+        0
+    }
+
+    /// Remembers the quiet move that failed high, so it's tried early in similar positions
+    fn record_cutoff(&mut self, mv: Move, depth: u8, ply: i32) {
+        if self.features.killer_moves {
+            let ply: usize = ply as usize;
+            if self.killers.len() <= ply {
+                self.killers.resize(ply + 1, [None; 2]);
+            }
+            let killers: &mut [Option<Move>; 2] = &mut self.killers[ply];
+            if killers[0] != Some(mv) {
+                killers[1] = killers[0];
+                killers[0] = Some(mv);
+            }
+        }
+        if self.features.history_heuristic {
+            // Cutoffs deep in the tree say more than ones near the leaves
+            let score: &mut i32 = &mut self.history_scores[mv.from.index()][mv.to.index()];
+            *score = score.saturating_add(depth as i32 * depth as i32);
+        }
+    }
+
+    /// Searches the root moves with the window `alpha` to `beta`, the first move scoring
+    /// at least `beta` ends the search<br />
+    /// Returns the best move with its score, a score at `alpha` or below only bounds it
+    fn search_root(
+        &mut self,
+        position: &Position,
+        moves: &[(Move, i32)],
+        depth: u8,
+        mut alpha: i32,
+        beta: i32,
+        noise: i32,
+    ) -> Option<(Move, i32)> {
+        let window_alpha: i32 = alpha;
+        let mut best: Option<(Move, i32)> = None;
+        let mut best_noisy_score: i32 = -MATE_SCORE;
+
+        for (mv, move_noise) in moves {
+            // Noisy scores need the exact score of every move, not just a bound
+            let child_beta: i32 = if noise > 0 { -window_alpha } else { -alpha };
+            let score: i32 = -self.alpha_beta(
+                &position.make_move(*mv),
+                depth - 1,
+                1,
+                -beta,
+                child_beta,
+                true,
+            );
+            if self.stopped {
+                return None;
+            }
+
+            if best.is_none() || score + move_noise > best_noisy_score {
+                alpha = alpha.max(score);
+                best = Some((*mv, score));
+                best_noisy_score = score + move_noise;
+            }
+            if score >= beta {
+                break;
+            }
+        }
+
+        best
+    }
+
+    /// Searches one iteration deeper each time, from `first_depth` until the depth limit<br />
+    /// Each iteration searches the best move of the previous one first,
+    /// `on_iteration` gets the result of each finished one
+    fn iterative_deepening(
+        &mut self,
+        position: &Position,
+        moves: &mut [(Move, i32)],
+        limits: SearchLimits,
+        first_depth: u8,
+        on_iteration: &mut dyn FnMut(&SearchResult),
+    ) -> SearchResult {
+        let mut result: SearchResult = SearchResult {
+            best_move: moves.first().map(|(mv, _)| *mv),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+        };
+
+        for depth in first_depth..=limits.depth.max(1) {
+            if self.stop.load(Ordering::Relaxed) || self.finished.load(Ordering::Relaxed) {
+                break;
+            }
+
+            // Score rarely moves much between iterations, a narrow window cuts off more<br />
+            // A score outside of it only bounds the real one, the iteration is searched again wider
+            let mut delta: i32 = ASPIRATION_WINDOW;
+            let (mut alpha, mut beta): (i32, i32) = if self.features.aspiration_windows
+                && limits.noise == 0
+                && result.depth >= 3
+                && result.score.abs() < TB_WIN_SCORE
+            {
+                (result.score - delta, result.score + delta)
+            } else {
+                (-MATE_SCORE, MATE_SCORE)
+            };
+
+            let best: Option<(Move, i32)> = loop {
+                let best: Option<(Move, i32)> =
+                    self.search_root(position, moves, depth, alpha, beta, limits.noise);
+                let Some((_, score)) = best else {
+                    break None;
+                };
+
+                delta *= 2;
+                if score <= alpha && alpha > -MATE_SCORE {
+                    alpha = (score - delta).max(-MATE_SCORE);
+                } else if score >= beta && beta < MATE_SCORE {
+                    beta = (score + delta).min(MATE_SCORE);
+                } else {
+                    break best;
+                }
+            };
+
+            if self.stopped {
+                break;
+            }
+
+            let Some((best_move, score)) = best else {
+                break;
+            };
+            result = SearchResult {
+                best_move: Some(best_move),
+                score,
+                depth,
+                nodes: self.count_nodes(),
+                time: self.started_at.elapsed(),
+            };
+            on_iteration(&result);
+
+            // Search the best move first in the next iteration
+            if let Some(index) = moves.iter().position(|(mv, _)| *mv == best_move) {
+                moves[..=index].rotate_right(1);
+            }
+
+            // Deeper iterations can't find a faster mate
+            if score.abs() >= MATE_THRESHOLD {
+                break;
+            }
+        }
+
+        result.nodes = self.count_nodes();
+        result.time = self.started_at.elapsed();
+        result
+    }
+}
+
+/// Returns a higher number for captures of more valuable pieces, then by less valuable pieces<br />
+/// https://www.chessprogramming.org/MVV-LVA
+fn mvv_lva(position: &Position, mv: Move) -> i32 {
+    let attacker: PieceType = position.board.piece_at(mv.from);
+    // En passant takes a pawn
+    let victim: usize = match position.board.piece_at(mv.to) {
+        PieceType::None if is_capture(position, mv) => 0,
+        // Promotion to a queen comes with captures of queens
+        PieceType::None => {
+            return mv
+                .promotion
+                .map_or(0, |promotion| promotion.kind_index() as i32 * 8)
+        }
+        victim => victim.kind_index(),
+    };
+    victim as i32 * 8 + 6 - attacker.kind_index() as i32
+}
+
+/// Returns true if the player has pieces other than pawns and the king
+fn has_pieces(position: &Position, color: PieceColor) -> bool {
+    (1..=4).any(|kind| {
+        position
+            .board
+            .pieces_of(PieceType::from_kind_index(kind, color))
+            != 0
+    })
+}
+
+/// Returns true if the move takes a piece, en passant included
+pub fn is_capture(position: &Position, mv: Move) -> bool {
+    position.board.piece_at(mv.to) != PieceType::None
+        || (position.board.piece_at(mv.from).is_pawn() && position.en_passant_square == Some(mv.to))
+}
+
+/// Finds the best move for the player to move with iterative deepening<br />
+/// Each iteration searches the best move of the previous one first,
+/// an iteration cut off by the time limit is thrown away
+pub fn search(position: &Position, limits: SearchLimits) -> SearchResult {
+    search_with_stop(
+        position,
+        &[],
+        limits,
+        &TranspositionTable::default(),
+        None,
+        &AtomicBool::new(false),
+        &mut |_| {},
+    )
+}
+
+/// Same as [`search`], but also stops as soon as `stop` is set, e.g. by another thread<br />
+/// `history` holds hashes of the game's positions, so the search sees repetitions,
+/// and `tt` keeps results between searches<br />
+/// Helper threads search the same position, each starting at another depth,
+/// and only share what they find through `tt`<br />
+/// With a `tablebase`, won and lost positions in it are played by the tables without searching
+/// and the search only picks among drawing moves, nodes in it score by the tables<br />
+/// `on_iteration` gets the result of each finished iteration while the search goes on<br />
+/// Returns the best move of the last finished iteration
+pub fn search_with_stop(
+    position: &Position,
+    history: &[u64],
+    limits: SearchLimits,
+    tt: &TranspositionTable,
+    tablebase: Option<&Tablebase>,
+    stop: &AtomicBool,
+    on_iteration: &mut dyn FnMut(&SearchResult),
+) -> SearchResult {
+    let started_at: Instant = Instant::now();
+
+    // Root is the last position of the history, nodes below it look for repetitions of it too
+    let mut history: Vec<u64> = history.to_vec();
+    if history.last() != Some(&position.hash()) {
+        history.push(position.hash());
+    }
+
+    // Noise of each root move stays the same in all iterations
+    let mut random: Random = Random(limits.seed | 1);
+    let mut moves: Vec<(Move, i32)> = generate_legal_moves(position)
+        .into_iter()
+        .map(|mv| {
+            let noise: i32 = if limits.noise > 0 {
+                (random.next() % (2 * limits.noise as u64 + 1)) as i32 - limits.noise
+            } else {
+                0
+            };
+            (mv, noise)
+        })
+        .collect();
+
+    let root_moves: Option<Vec<RootMove>> =
+        tablebase.and_then(|tablebase| tablebase.probe_root(position));
+    if let Some(root_moves) = root_moves {
+        let best_rank: i32 = root_moves
+            .iter()
+            .map(|root_move| root_move.rank)
+            .max()
+            .unwrap_or(0);
+        // Fastest win, or slowest loss
+        if let Some(best) = root_moves
+            .iter()
+            .filter(|root_move| root_move.rank == best_rank && best_rank != 0)
+            .min_by_key(|root_move| root_move.dtz)
+        {
+            let result: SearchResult = SearchResult {
+                best_move: Some(best.mv),
+                score: tablebase_score(best),
+                depth: 0,
+                nodes: 0,
+                time: started_at.elapsed(),
+            };
+            on_iteration(&result);
+            return result;
+        }
+        moves.retain(|(mv, _)| {
+            root_moves
+                .iter()
+                .any(|root_move| root_move.mv == *mv && root_move.rank == 0)
+        });
+    }
+
+    let finished: AtomicBool = AtomicBool::new(false);
+    let all_nodes: AtomicU64 = AtomicU64::new(0);
+    let new_searcher = || Searcher {
+        nodes: 0,
+        all_nodes: &all_nodes,
+        counted_nodes: 0,
+        started_at,
+        deadline: limits.time.map(|time| started_at + time),
+        stop,
+        finished: &finished,
+        stopped: false,
+        tt,
+        history: history.clone(),
+        tablebase,
+        features: limits.features,
+        killers: Vec::new(),
+        history_scores: Box::new([[0; 64]; 64]),
+    };
+
+    std::thread::scope(|scope| {
+        // Odd helpers start one ply deeper, so threads don't all search the same depth
+        let helpers: Vec<ScopedJoinHandle<()>> = (1..limits.threads.max(1))
+            .map(|thread| {
+                let mut searcher: Searcher = new_searcher();
+                let mut moves: Vec<(Move, i32)> = moves.iter().map(|(mv, _)| (*mv, 0)).collect();
+                let limits: SearchLimits = SearchLimits { noise: 0, ..limits };
+                scope.spawn(move || {
+                    searcher.iterative_deepening(
+                        position,
+                        &mut moves,
+                        limits,
+                        1 + (thread % 2) as u8,
+                        &mut |_| {},
+                    );
+                })
+            })
+            .collect();
+
+        let mut searcher: Searcher = new_searcher();
+        let mut result: SearchResult =
+            searcher.iterative_deepening(position, &mut moves, limits, 1, on_iteration);
+        finished.store(true, Ordering::Relaxed);
+
+        for helper in helpers {
+            let _ = helper.join();
+        }
+        result.nodes = all_nodes.load(Ordering::Relaxed);
+        result.time = started_at.elapsed();
+        result
+    })
+}
+
+/// Scores a tablebase root move, wins the fifty-move rule may still draw score only a little
+fn tablebase_score(root_move: &RootMove) -> i32 {
+    match root_move.rank {
+        900.. => TB_WIN_SCORE - root_move.dtz,
+        1..=899 => (root_move.rank - 800).max(3) * PAWN_VALUE / 200,
+        0 => 0,
+        -899..=-1 => (root_move.rank + 800).min(-3) * PAWN_VALUE / 200,
+        _ => -TB_WIN_SCORE - root_move.dtz,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best_move(fen: &str, depth: u8) -> SearchResult {
+        search(
+            &Position::from_fen(fen).unwrap(),
+            SearchLimits {
+                depth,
+                time: None,
+                ..SearchLimits::default()
+            },
+        )
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let result: SearchResult = best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(
+            result.best_move.map(|mv| mv.to),
+            Some("a8".parse().unwrap())
+        );
+        assert_eq!(result.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn finds_smothered_mate() {
+        let result: SearchResult = best_move("6rk/6pp/8/6N1/8/8/8/6K1 w - - 0 1", 3);
+        assert_eq!(
+            result.best_move.map(|mv| mv.to),
+            Some("f7".parse().unwrap())
+        );
+        assert_eq!(result.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        let result: SearchResult = best_move("k7/8/2K5/8/8/8/8/7R w - - 0 1", 4);
+        assert_eq!(result.mate_in(), Some(2));
+
+        // Side getting mated sees it too
+        let result: SearchResult = best_move("k7/8/1K6/8/8/8/8/7R b - - 0 1", 3);
+        assert_eq!(result.mate_in(), Some(-1));
+    }
+
+    #[test]
+    fn wins_hanging_queen() {
+        let result: SearchResult = best_move("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 2);
+        assert_eq!(
+            result.best_move.map(|mv| mv.to),
+            Some("d5".parse().unwrap())
+        );
+        assert!(result.score > 4 * PAWN_VALUE);
+    }
+
+    #[test]
+    fn sees_recapture_in_quiescence() {
+        // Rook takes a defended pawn only at depth 1, quiescence sees the recapture
+        let result: SearchResult = best_move("4k3/2p5/3p4/8/8/8/8/3RK3 w - - 0 1", 1);
+        assert_ne!(
+            result.best_move.map(|mv| mv.to),
+            Some("d6".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn searches_with_threads() {
+        let position: Position = Position::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let limits: SearchLimits = SearchLimits {
+            depth: 4,
+            time: None,
+            ..SearchLimits::default()
+        };
+        let mut iterations: Vec<SearchResult> = Vec::new();
+        let parallel: SearchResult = search_with_stop(
+            &position,
+            &[],
+            SearchLimits {
+                threads: 4,
+                ..limits
+            },
+            &TranspositionTable::new(1),
+            None,
+            &AtomicBool::new(false),
+            &mut |iteration| iterations.push(*iteration),
+        );
+
+        assert_eq!(parallel.mate_in(), Some(2));
+        // Helpers' nodes count too, also in each iteration
+        assert!(parallel.nodes > 0);
+        assert!(parallel.nodes_per_second() > 0);
+        assert!(iterations
+            .windows(2)
+            .all(|pair| pair[0].nodes <= pair[1].nodes));
+        assert!(iterations.last().unwrap().nodes <= parallel.nodes);
+    }
+
+    #[test]
+    fn finds_mates_with_each_feature_off() {
+        for (name, _) in SearchFeatures::default().options() {
+            let mut features: SearchFeatures = SearchFeatures::default();
+            *features.option_mut(name).unwrap() = false;
+            for (fen, depth, mate_in) in [
+                ("6rk/6pp/8/6N1/8/8/8/6K1 w - - 0 1", 3, 1),
+                ("k7/8/2K5/8/8/8/8/7R w - - 0 1", 4, 2),
+            ] {
+                let result: SearchResult = search(
+                    &Position::from_fen(fen).unwrap(),
+                    SearchLimits {
+                        depth,
+                        time: None,
+                        features,
+                        ..SearchLimits::default()
+                    },
+                );
+                assert_eq!(result.mate_in(), Some(mate_in), "{name} off, {fen}");
+            }
+        }
+    }
+
+    #[test]
+    fn features_search_fewer_nodes() {
+        let position: Position = Position::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        )
+        .unwrap();
+        let limits: SearchLimits = SearchLimits {
+            depth: 5,
+            time: None,
+            ..SearchLimits::default()
+        };
+        let plain: SearchResult = search(
+            &position,
+            SearchLimits {
+                features: SearchFeatures {
+                    mvv_lva: true,
+                    ..SearchFeatures::NONE
+                },
+                ..limits
+            },
+        );
+        let enhanced: SearchResult = search(&position, limits);
+
+        assert_eq!(enhanced.depth, 5);
+        assert!(enhanced.nodes * 2 < plain.nodes);
+    }
+
+    #[test]
+    fn reports_each_iteration() {
+        let mut iterations: Vec<SearchResult> = Vec::new();
+        let result: SearchResult = search_with_stop(
+            &Position::new(),
+            &[],
+            SearchLimits {
+                depth: 4,
+                time: None,
+                ..SearchLimits::default()
+            },
+            &TranspositionTable::new(1),
+            None,
+            &AtomicBool::new(false),
+            &mut |iteration| iterations.push(*iteration),
+        );
+
+        let depths: Vec<u8> = iterations.iter().map(|iteration| iteration.depth).collect();
+        assert_eq!(depths, [1, 2, 3, 4]);
+        assert!(iterations
+            .windows(2)
+            .all(|pair| pair[0].nodes < pair[1].nodes && pair[0].time <= pair[1].time));
+        assert_eq!(
+            iterations.last().map(|last| last.best_move),
+            Some(result.best_move)
+        );
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let result: SearchResult = search_with_stop(
+            &Position::new(),
+            &[],
+            SearchLimits {
+                depth: 20,
+                time: None,
+                ..SearchLimits::default()
+            },
+            &TranspositionTable::new(1),
+            None,
+            &AtomicBool::new(true),
+            &mut |_| {},
+        );
+        assert_eq!(result.depth, 0);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn noise_varies_the_move() {
+        let position: Position = Position::new();
+        let moves: Vec<Option<Move>> = (0..8)
+            .map(|seed| {
+                let limits: SearchLimits = SearchLimits {
+                    depth: 1,
+                    time: None,
+                    noise: 100,
+                    seed,
+                    ..SearchLimits::default()
+                };
+                search(&position, limits).best_move
+            })
+            .collect();
+        assert!(moves.iter().any(|mv| *mv != moves[0]));
+
+        // Small noise doesn't give away a queen
+        let position: Position = Position::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        for seed in 0..8 {
+            let limits: SearchLimits = SearchLimits {
+                depth: 2,
+                time: None,
+                noise: 50,
+                seed,
+                ..SearchLimits::default()
+            };
+            assert_eq!(
+                search(&position, limits).best_move.map(|mv| mv.to),
+                Some("d5".parse().unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn sees_repetition_as_draw() {
+        // Black is a queen down but can go back to a position of the game
+        let position: Position = Position::from_fen("6nk/8/8/8/8/8/8/3Q2K1 b - - 10 30").unwrap();
+        let repeated: Position = Position::from_fen("7k/8/5n2/8/8/8/8/3Q2K1 w - - 11 31").unwrap();
+
+        let result: SearchResult = search_with_stop(
+            &position,
+            &[repeated.hash(), position.hash()],
+            SearchLimits {
+                depth: 2,
+                time: None,
+                ..SearchLimits::default()
+            },
+            &TranspositionTable::new(1),
+            None,
+            &AtomicBool::new(false),
+            &mut |_| {},
+        );
+        assert_eq!(result.score, 0);
+        assert_eq!(
+            result.best_move.map(|mv| mv.to),
+            Some("f6".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn reports_no_move_when_mated() {
+        let result: SearchResult = best_move(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            3,
+        );
+        assert_eq!(result.best_move, None);
+    }
+}
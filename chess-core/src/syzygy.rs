@@ -0,0 +1,1409 @@
+use crate::*;
+use std::{collections::HashMap, ffi::OsStr, path::PathBuf, sync::OnceLock};
+
+/// Most pieces a Syzygy table can hold, kings included
+const MAX_PIECES: usize = 7;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// Flags of a table's compressed data
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// Result of a position with perfect play, from the side to move's point of view<br />
+/// Cursed wins and blessed losses are wins and losses the fifty-move rule turns into draws
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Wdl::Loss => "loss",
+            Wdl::BlessedLoss => "blessed loss",
+            Wdl::Draw => "draw",
+            Wdl::CursedWin => "cursed win",
+            Wdl::Win => "win",
+        }
+    }
+
+    /// Returns the result from -2 for a loss to 2 for a win, like the tables store it
+    fn value(&self) -> i32 {
+        match self {
+            Wdl::Loss => -2,
+            Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 1,
+            Wdl::Win => 2,
+        }
+    }
+
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+}
+
+impl std::ops::Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        Wdl::from_value(-self.value())
+    }
+}
+
+/// Distance to zeroing of the move that resets the fifty-move counter, given the result after it
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+/// Tablebase result of a root move, counted from the position before the move
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RootMove {
+    pub mv: Move,
+    /// Plies to the next capture or pawn move with perfect play, positive if the move wins,
+    /// negative if it loses and 0 if it draws
+    pub dtz: i32,
+    /// Higher is better, wins ranked equally unless the fifty-move rule is in sight
+    pub rank: i32,
+}
+
+/// Tables mapping piece placements to indices, shared by all tables
+struct EncodingTables {
+    /// Pawn squares a2-h7 to 0..47, the leading pawn is the one with the highest value
+    map_pawns: [usize; 64],
+    /// Squares below the a1-h8 diagonal to 0..27
+    map_b1h1h7: [usize; 64],
+    /// Squares of the a1-d1-d4 triangle to 0..9, the diagonal last
+    map_a1d1d4: [usize; 64],
+    /// The 462 placements of two kings with the first one in the a1-d1-d4 triangle
+    map_kk: [[u64; 64]; 10],
+    binomial: [[u64; 64]; 6],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+static ENCODING_TABLES: OnceLock<EncodingTables> = OnceLock::new();
+
+fn encoding() -> &'static EncodingTables {
+    ENCODING_TABLES.get_or_init(EncodingTables::new)
+}
+
+/// Rank minus file, 0 on the a1-h8 diagonal and negative below it
+fn off_diagonal(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+impl EncodingTables {
+    fn new() -> Self {
+        let mut tables: EncodingTables = EncodingTables {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; 6],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+
+        let mut code: usize = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                tables.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        // Up to d4, squares below the diagonal come first
+        let mut diagonal: Vec<usize> = Vec::new();
+        code = 0;
+        for square in 0..=27 {
+            if square % 8 > 3 {
+                continue;
+            }
+            if off_diagonal(square) < 0 {
+                tables.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            tables.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // Kings can't touch, a first king on the diagonal keeps the second one on or below it,
+        // placements with both kings on the diagonal come last
+        let mut both_on_diagonal: Vec<(usize, usize)> = Vec::new();
+        let mut code: u64 = 0;
+        for idx in 0..10 {
+            for first in 0..=27 {
+                if tables.map_a1d1d4[first] != idx || (idx == 0 && first != 1) {
+                    continue;
+                }
+
+                let first_square: Square = Square::from_index(first);
+                let touching: Bitboard = king_attacks(first_square) | first_square.bitboard();
+                for second in 0..64 {
+                    if touching & (1 << second) != 0
+                        || (off_diagonal(first) == 0 && off_diagonal(second) > 0)
+                    {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        tables.map_kk[idx][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            tables.map_kk[idx][second] = code;
+            code += 1;
+        }
+
+        // binomial[k][n] ways to choose k of n squares
+        tables.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                tables.binomial[k][n] = if k > 0 {
+                    tables.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n { tables.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // Leading pawn goes from the edge files inwards and from rank 2 upwards,
+        // other pawns can't be nearer the edge or lower than it
+        let mut available_squares: usize = 48;
+        for lead_pawns in 1..=5 {
+            for file in 0..4 {
+                let mut idx: u64 = 0;
+                for rank in 1..=6 {
+                    let square: usize = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        available_squares -= 1;
+                        tables.map_pawns[square] = available_squares;
+                        available_squares = available_squares.saturating_sub(1);
+                        tables.map_pawns[square ^ 7] = available_squares;
+                    }
+                    tables.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += tables.binomial[lead_pawns - 1][tables.map_pawns[square]];
+                }
+                tables.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+
+        tables
+    }
+}
+
+/// Pieces of both sides of a table, white is the side written first in the file name
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Material {
+    /// Number of pieces by color and kind, like `PieceType::kind_index`
+    counts: [[u8; 6]; 2],
+}
+
+impl Material {
+    /// Parses a table name like `KRvK`
+    fn from_name(name: &str) -> Option<Material> {
+        let (white, black): (&str, &str) = name.split_once('v')?;
+        let mut counts: [[u8; 6]; 2] = [[0; 6]; 2];
+        for (color, side) in [white, black].iter().enumerate() {
+            for c in side.chars() {
+                let kind: usize = piece_type_from_fen(c)
+                    .filter(|piece_type| piece_type.color() == Some(PieceColor::White))?
+                    .kind_index();
+                counts[color][kind] += 1;
+            }
+        }
+
+        let material: Material = Material { counts };
+        (counts[0][5] == 1 && counts[1][5] == 1 && material.piece_count() <= MAX_PIECES)
+            .then_some(material)
+    }
+
+    fn from_position(position: &Position) -> Material {
+        let mut counts: [[u8; 6]; 2] = [[0; 6]; 2];
+        for piece in position.pieces() {
+            counts[piece.color.index()][piece.piece_type.kind_index()] += 1;
+        }
+        Material { counts }
+    }
+
+    /// Returns the table name, kings first then from queens to pawns
+    fn name(&self) -> String {
+        let side = |counts: &[u8; 6]| -> String {
+            [5, 4, 3, 2, 1, 0]
+                .iter()
+                .flat_map(|kind| {
+                    let c: char =
+                        piece_type_to_fen(PieceType::from_kind_index(*kind, PieceColor::White))
+                            .unwrap_or('?');
+                    std::iter::repeat_n(c, counts[*kind] as usize)
+                })
+                .collect()
+        };
+        format!("{}v{}", side(&self.counts[0]), side(&self.counts[1]))
+    }
+
+    fn flipped(&self) -> Material {
+        Material {
+            counts: [self.counts[1], self.counts[0]],
+        }
+    }
+
+    fn piece_count(&self) -> usize {
+        self.counts
+            .iter()
+            .flatten()
+            .map(|count| *count as usize)
+            .sum()
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.counts[0][0] + self.counts[1][0] > 0
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+
+    /// True if any side has a piece other than the king only once
+    fn has_unique_pieces(&self) -> bool {
+        self.counts.iter().any(|counts| counts[..5].contains(&1))
+    }
+
+    /// Pawns of the leading color and of the other one, the side with fewer pawns leads
+    fn pawn_counts(&self) -> [usize; 2] {
+        let (white, black): (usize, usize) =
+            (self.counts[0][0] as usize, self.counts[1][0] as usize);
+        if black == 0 || (white > 0 && black >= white) {
+            [white, black]
+        } else {
+            [black, white]
+        }
+    }
+}
+
+/// Piece as the tables write it, kind from 1 for pawns to 6 for kings plus 8 for black
+fn piece_code(piece_type: PieceType) -> u8 {
+    let color: u8 = match piece_type.color() {
+        Some(PieceColor::Black) => 8,
+        _ => 0,
+    };
+    piece_type.kind_index() as u8 + 1 + color
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/// Value read from a table
+enum TableValue {
+    Value(i32),
+    /// DTZ tables only store one side to move, the position has the other one
+    ChangeStm,
+}
+
+/// Compressed values of one side to move and leading file of a table,
+/// offsets point into the table's bytes
+#[derive(Clone, Debug, Default)]
+struct PairsData {
+    flags: u8,
+    block_size: usize,
+    /// About every span values there is a sparse index entry
+    span: u64,
+    num_blocks: usize,
+    /// Shortest Huffman symbol in bits, or the value of a single value table
+    min_sym_len: u8,
+    lowest_sym: usize,
+    btree: usize,
+    block_length: usize,
+    block_length_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    /// Lowest symbol of each length, left aligned in 64 bits
+    base64: Vec<u64>,
+    /// Number of values minus one each symbol expands to
+    symlen: Vec<u8>,
+    /// Order of the pieces, defining the groups they are encoded in
+    pieces: [u8; MAX_PIECES],
+    group_idx: [u64; MAX_PIECES + 1],
+    /// Number of pieces in each group, zero terminated
+    group_len: [usize; MAX_PIECES + 1],
+    /// Where the DTZ values of wins, losses, cursed wins and blessed losses start in the map
+    map_idx: [u16; 4],
+}
+
+/// WDL or DTZ table read whole into memory<br />
+/// File format: https://github.com/syzygy1/tb
+struct Table {
+    bytes: Vec<u8>,
+    kind: TableKind,
+    material: Material,
+    /// Compressed data by side to move and leading pawn file
+    items: Vec<Vec<PairsData>>,
+    /// Start of the DTZ value map
+    map: usize,
+}
+
+impl Table {
+    /// Returns the bytes at the offset, zeros past the end of the file
+    fn read<const N: usize>(&self, offset: usize) -> [u8; N] {
+        self.bytes
+            .get(offset..offset + N)
+            .and_then(|bytes| bytes.try_into().ok())
+            .unwrap_or([0; N])
+    }
+
+    fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_le_bytes(self.read(offset))
+    }
+
+    /// Parses the table's header, None if it isn't a table of the material
+    fn parse(bytes: Vec<u8>, kind: TableKind, material: Material) -> Option<Table> {
+        let magic: [u8; 4] = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if bytes.get(0..4)? != magic {
+            return None;
+        }
+
+        let flags: u8 = *bytes.get(4)?;
+        let split: bool = !material.is_symmetric();
+        if (flags & 1 != 0) != split || (flags & 2 != 0) != material.has_pawns() {
+            return None;
+        }
+
+        let sides: usize = if kind == TableKind::Wdl && split {
+            2
+        } else {
+            1
+        };
+        let files: usize = if material.has_pawns() { 4 } else { 1 };
+        let pawns_on_both_sides: bool = material.has_pawns() && material.pawn_counts()[1] > 0;
+        let mut table: Table = Table {
+            bytes,
+            kind,
+            material,
+            items: vec![vec![PairsData::default(); files]; sides],
+            map: 0,
+        };
+
+        let mut offset: usize = 5;
+        for file in 0..files {
+            let [first, second]: [u8; 2] = table.read(offset);
+            let second: u8 = if pawns_on_both_sides { second } else { 0xFF };
+            let orders: [[u8; 2]; 2] = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            offset += 1 + pawns_on_both_sides as usize;
+
+            for k in 0..material.piece_count() {
+                let [code]: [u8; 1] = table.read(offset);
+                for (side, item) in table.items.iter_mut().enumerate() {
+                    item[file].pieces[k] = if side == 1 { code >> 4 } else { code & 0xF };
+                }
+                offset += 1;
+            }
+            for (side, item) in table.items.iter_mut().enumerate() {
+                item[file].set_groups(&material, orders[side], file);
+            }
+        }
+        offset += offset & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                offset = table.set_sizes(side, file, offset)?;
+            }
+        }
+
+        if kind == TableKind::Dtz {
+            table.map = offset;
+            for file in 0..files {
+                let flags: u8 = table.items[0][file].flags;
+                if flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                for i in 0..4 {
+                    if flags & FLAG_WIDE != 0 {
+                        offset += offset & 1;
+                        table.items[0][file].map_idx[i] = ((offset - table.map) / 2 + 1) as u16;
+                        offset += 2 * table.read_u16(offset) as usize + 2;
+                    } else {
+                        table.items[0][file].map_idx[i] = (offset - table.map + 1) as u16;
+                        offset += table.read::<1>(offset)[0] as usize + 1;
+                    }
+                }
+            }
+            offset += offset & 1;
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let item: &mut PairsData = &mut table.items[side][file];
+                item.sparse_index = offset;
+                offset += item.sparse_index_size * 6;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let item: &mut PairsData = &mut table.items[side][file];
+                item.block_length = offset;
+                offset += item.block_length_size * 2;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                // Single value tables have no blocks and may end before the alignment
+                let item: &mut PairsData = &mut table.items[side][file];
+                if item.num_blocks > 0 {
+                    offset = (offset + 0x3F) & !0x3F;
+                    item.data = offset;
+                    offset += item.num_blocks * item.block_size;
+                }
+            }
+        }
+
+        (offset <= table.bytes.len()).then_some(table)
+    }
+
+    /// Reads the sizes of the compressed data and its Huffman code, returns where the next one starts
+    fn set_sizes(&mut self, side: usize, file: usize, mut offset: usize) -> Option<usize> {
+        let [flags, value]: [u8; 2] = self.read(offset);
+        let mut item: PairsData = std::mem::take(&mut self.items[side][file]);
+        item.flags = flags;
+        offset += 1;
+
+        if flags & FLAG_SINGLE_VALUE != 0 {
+            item.min_sym_len = value;
+            self.items[side][file] = item;
+            return Some(offset + 1);
+        }
+
+        // Index of the last group is the number of positions
+        let groups: usize = item.group_len.iter().position(|len| *len == 0)?;
+        let size: u64 = item.group_idx[groups];
+
+        let [block_size, span, padding]: [u8; 3] = self.read(offset);
+        item.block_size = 1 << block_size;
+        item.span = 1 << span;
+        item.sparse_index_size = size.div_ceil(item.span) as usize;
+        offset += 3;
+        item.num_blocks = u32::from_le_bytes(self.read(offset)) as usize;
+        item.block_length_size = item.num_blocks + padding as usize;
+        offset += 4;
+
+        let [max_sym_len, min_sym_len]: [u8; 2] = self.read(offset);
+        if max_sym_len < min_sym_len || max_sym_len > 64 || min_sym_len == 0 {
+            return None;
+        }
+        item.min_sym_len = min_sym_len;
+        offset += 2;
+        item.lowest_sym = offset;
+
+        // Canonical code with longer symbols having lower values
+        let lengths: usize = (max_sym_len - min_sym_len) as usize + 1;
+        item.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            item.base64[i] = item.base64[i + 1]
+                .wrapping_add(self.read_u16(item.lowest_sym + 2 * i) as u64)
+                .wrapping_sub(self.read_u16(item.lowest_sym + 2 * (i + 1)) as u64)
+                / 2;
+        }
+        for (i, base) in item.base64.iter_mut().enumerate() {
+            *base = base
+                .checked_shl(64 - i as u32 - min_sym_len as u32)
+                .unwrap_or(0);
+        }
+        offset += lengths * 2;
+
+        // Symbols stand for pairs of smaller symbols, "Recursive Pairing"
+        let symbols: usize = self.read_u16(offset) as usize;
+        offset += 2;
+        item.btree = offset;
+        if offset + symbols * 3 > self.bytes.len() {
+            return None;
+        }
+        item.symlen = vec![0; symbols];
+        let mut visited: Vec<bool> = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                item.symlen[symbol] = self.set_symlen(&mut item, symbol, &mut visited);
+            }
+        }
+
+        self.items[side][file] = item;
+        Some(offset + symbols * 3 + (symbols & 1))
+    }
+
+    fn set_symlen(&self, item: &mut PairsData, symbol: usize, visited: &mut [bool]) -> u8 {
+        visited[symbol] = true;
+        let right: usize = self.right_symbol(item, symbol);
+        if right == 0xFFF {
+            return 0;
+        }
+        let left: usize = self.left_symbol(item, symbol);
+        if left >= visited.len() || right >= visited.len() {
+            return 0;
+        }
+
+        if !visited[left] {
+            item.symlen[left] = self.set_symlen(item, left, visited);
+        }
+        if !visited[right] {
+            item.symlen[right] = self.set_symlen(item, right, visited);
+        }
+        item.symlen[left]
+            .wrapping_add(item.symlen[right])
+            .wrapping_add(1)
+    }
+
+    fn left_symbol(&self, item: &PairsData, symbol: usize) -> usize {
+        let [a, b, _]: [u8; 3] = self.read(item.btree + 3 * symbol);
+        ((b as usize & 0xF) << 8) | a as usize
+    }
+
+    fn right_symbol(&self, item: &PairsData, symbol: usize) -> usize {
+        let [_, b, c]: [u8; 3] = self.read(item.btree + 3 * symbol);
+        ((c as usize) << 4) | (b as usize >> 4)
+    }
+
+    fn get(&self, stm: usize, file: usize) -> &PairsData {
+        let item: &Vec<PairsData> = &self.items[stm % self.items.len()];
+        &item[file.min(item.len() - 1)]
+    }
+
+    /// Returns the value stored at the index
+    fn decompress_pairs(&self, item: &PairsData, idx: u64) -> usize {
+        if item.flags & FLAG_SINGLE_VALUE != 0 {
+            return item.min_sym_len as usize;
+        }
+
+        // Sparse index points to a block and an offset near the value
+        let k: usize = (idx / item.span) as usize;
+        let entry: usize = item.sparse_index + 6 * k;
+        let mut block: usize = u32::from_le_bytes(self.read(entry)) as usize;
+        let mut offset: i64 = self.read_u16(entry + 4) as i64;
+        offset += (idx % item.span) as i64 - (item.span / 2) as i64;
+
+        let block_length =
+            |block: usize| -> i64 { self.read_u16(item.block_length + 2 * block) as i64 };
+        while offset < 0 && block > 0 {
+            block -= 1;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) && block < item.num_blocks {
+            offset -= block_length(block) + 1;
+            block += 1;
+        }
+
+        // Walk the block's Huffman symbols until the one covering the offset
+        let mut pointer: usize = item.data + block * item.block_size;
+        let mut buffer: u64 = u64::from_be_bytes(self.read(pointer));
+        pointer += 8;
+        let mut buffer_size: usize = 64;
+        let mut symbol: usize;
+        loop {
+            let mut length: usize = 0;
+            while length + 1 < item.base64.len() && buffer < item.base64[length] {
+                length += 1;
+            }
+            symbol = buffer
+                .wrapping_sub(item.base64[length])
+                .checked_shr((64 - length - item.min_sym_len as usize) as u32)
+                .unwrap_or(0) as usize;
+            symbol = (symbol + self.read_u16(item.lowest_sym + 2 * length) as usize) & 0xFFFF;
+            if symbol >= item.symlen.len() {
+                return 0;
+            }
+
+            if offset < item.symlen[symbol] as i64 + 1 {
+                break;
+            }
+            offset -= item.symlen[symbol] as i64 + 1;
+            length += item.min_sym_len as usize;
+            buffer = buffer.checked_shl(length as u32).unwrap_or(0);
+            buffer_size = buffer_size.saturating_sub(length);
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (u32::from_be_bytes(self.read(pointer)) as u64) << (64 - buffer_size);
+                pointer += 4;
+            }
+        }
+
+        // Expand the symbol into its pair until reaching a single value
+        while item.symlen[symbol] != 0 {
+            let left: usize = self.left_symbol(item, symbol);
+            if left >= item.symlen.len() {
+                return 0;
+            }
+            if offset < item.symlen[left] as i64 + 1 {
+                symbol = left;
+            } else {
+                offset -= item.symlen[left] as i64 + 1;
+                symbol = self.right_symbol(item, symbol);
+                if symbol >= item.symlen.len() {
+                    return 0;
+                }
+            }
+        }
+
+        self.left_symbol(item, symbol)
+    }
+
+    /// Turns a stored value into a WDL value or a DTZ in plies
+    fn map_score(&self, file: usize, value: usize, wdl: Wdl) -> i32 {
+        if self.kind == TableKind::Wdl {
+            return value as i32 - 2;
+        }
+
+        let item: &PairsData = self.get(0, file);
+        let mut value: i32 = value as i32;
+        if item.flags & FLAG_MAPPED != 0 {
+            let map_index: usize = match wdl {
+                Wdl::Loss => 1,
+                Wdl::BlessedLoss => 3,
+                Wdl::CursedWin => 2,
+                Wdl::Draw | Wdl::Win => 0,
+            };
+            let index: usize = item.map_idx[map_index] as usize + value as usize;
+            value = if item.flags & FLAG_WIDE != 0 {
+                self.read_u16(self.map + 2 * index) as i32
+            } else {
+                self.read::<1>(self.map + index)[0] as i32
+            };
+        }
+
+        // Stored in moves unless the flags say plies
+        if (wdl == Wdl::Win && item.flags & FLAG_WIN_PLIES == 0)
+            || (wdl == Wdl::Loss && item.flags & FLAG_LOSS_PLIES == 0)
+            || wdl == Wdl::CursedWin
+            || wdl == Wdl::BlessedLoss
+        {
+            value *= 2;
+        }
+        value + 1
+    }
+
+    /// Encodes the position into the table's index and reads its value
+    fn probe(&self, position: &Position, black_stronger: bool, wdl: Wdl) -> TableValue {
+        let encoding: &EncodingTables = encoding();
+
+        // Tables have white as the stronger side, symmetric ones only white to move,
+        // other positions are looked up with colors swapped and the board flipped
+        let flip: bool = black_stronger
+            || (self.material.is_symmetric() && position.color_to_move == PieceColor::Black);
+        let flip_color: u8 = if flip { 8 } else { 0 };
+        let flip_squares: usize = if flip { 56 } else { 0 };
+        let stm: usize = flip as usize ^ position.color_to_move.index();
+
+        let mut squares: [usize; MAX_PIECES] = [0; MAX_PIECES];
+        let mut pieces: [u8; MAX_PIECES] = [0; MAX_PIECES];
+        let mut size: usize = 0;
+        let mut lead_pawn_count: usize = 0;
+        let mut lead_pawns: Bitboard = 0;
+        let mut file: usize = 0;
+
+        // Pawn tables are split by the file of the leading pawn, mirrored to files a to d
+        let has_pawns: bool = self.material.has_pawns();
+        if has_pawns {
+            let lead_color: PieceColor = if (self.items[0][0].pieces[0] ^ flip_color) < 8 {
+                PieceColor::White
+            } else {
+                PieceColor::Black
+            };
+            lead_pawns = position
+                .board
+                .pieces_of(PieceType::PawnWhite.with_color(lead_color));
+            for square in squares_of(lead_pawns) {
+                squares[size] = square ^ flip_squares;
+                size += 1;
+            }
+            lead_pawn_count = size;
+
+            let lead: usize = (0..lead_pawn_count)
+                .max_by_key(|i| encoding.map_pawns[squares[*i]])
+                .unwrap_or(0);
+            squares.swap(0, lead);
+            file = squares[0] % 8;
+            if file > 3 {
+                file = (squares[0] ^ 7) % 8;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            let flags: u8 = self.get(stm, file).flags;
+            // Symmetric pawnless tables store one side only
+            let symmetric: bool = self.material.is_symmetric() && !has_pawns;
+            if (flags & FLAG_STM) as usize != stm && !symmetric {
+                return TableValue::ChangeStm;
+            }
+        }
+
+        for square in squares_of(position.board.occupied() ^ lead_pawns) {
+            if size == MAX_PIECES {
+                break;
+            }
+            squares[size] = square ^ flip_squares;
+            pieces[size] =
+                piece_code(position.board.piece_at(Square::from_index(square))) ^ flip_color;
+            size += 1;
+        }
+
+        // Same order of pieces as the table
+        let item: &PairsData = self.get(stm, file);
+        for i in lead_pawn_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|j| item.pieces[i] == pieces[*j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // Leading piece in files a to d
+        if squares[0] % 8 > 3 {
+            for square in &mut squares[..size] {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx: u64;
+        if has_pawns {
+            idx = encoding.lead_pawn_idx[lead_pawn_count][squares[0]];
+            squares[1..lead_pawn_count].sort_by_key(|square| encoding.map_pawns[*square]);
+            for (i, square) in squares.iter().enumerate().take(lead_pawn_count).skip(1) {
+                idx += encoding.binomial[i][encoding.map_pawns[*square]];
+            }
+        } else {
+            // Leading piece in ranks 1 to 4, then below the a1-h8 diagonal
+            if squares[0] / 8 > 3 {
+                for square in &mut squares[..size] {
+                    *square ^= 56;
+                }
+            }
+            for i in 0..item.group_len[0] {
+                let off: i32 = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for square in &mut squares[i..size] {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            idx = if self.material.has_unique_pieces() {
+                // Three unique pieces are encoded together, squares taken by earlier ones skipped
+                let [s0, s1, s2]: [usize; 3] = [squares[0], squares[1], squares[2]];
+                let adjust1: usize = (s1 > s0) as usize;
+                let adjust2: usize = (s2 > s0) as usize + (s2 > s1) as usize;
+                let rank = |square: usize| square / 8;
+                (if off_diagonal(s0) != 0 {
+                    (encoding.map_a1d1d4[s0] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+                } else if off_diagonal(s1) != 0 {
+                    (6 * 63 + rank(s0) * 28 + encoding.map_b1h1h7[s1]) * 62 + s2 - adjust2
+                } else if off_diagonal(s2) != 0 {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + rank(s0) * 7 * 28
+                        + (rank(s1) - adjust1) * 28
+                        + encoding.map_b1h1h7[s2]
+                } else {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + rank(s0) * 7 * 6
+                        + (rank(s1) - adjust1) * 6
+                        + (rank(s2) - adjust2)
+                }) as u64
+            } else {
+                encoding.map_kk[encoding.map_a1d1d4[squares[0]]][squares[1]]
+            };
+        }
+
+        // Remaining groups by ascending square, squares of earlier groups skipped
+        idx *= item.group_idx[0];
+        let mut group_start: usize = item.group_len[0];
+        let mut remaining_pawns: bool = has_pawns && self.material.pawn_counts()[1] > 0;
+        let mut next: usize = 1;
+        while next < item.group_len.len() && item.group_len[next] != 0 {
+            let group_end: usize = (group_start + item.group_len[next]).min(size);
+            squares[group_start..group_end].sort();
+
+            let mut n: u64 = 0;
+            for i in 0..group_end - group_start {
+                let square: usize = squares[group_start + i];
+                let adjust: usize = squares[..group_start]
+                    .iter()
+                    .filter(|earlier| square > **earlier)
+                    .count();
+                let free: usize = (square - adjust).saturating_sub(8 * remaining_pawns as usize);
+                n += encoding.binomial[i + 1][free];
+            }
+
+            remaining_pawns = false;
+            idx += n * item.group_idx[next];
+            group_start = group_end;
+            next += 1;
+        }
+
+        TableValue::Value(self.map_score(file, self.decompress_pairs(item, idx), wdl))
+    }
+}
+
+/// Iterates over the square indices of a bitboard, from a1 to h8
+fn squares_of(bitboard: Bitboard) -> impl Iterator<Item = usize> {
+    squares(bitboard).map(|square| square.index())
+}
+
+impl PairsData {
+    /// Splits the pieces into the groups they are encoded in and computes the factor of each group
+    fn set_groups(&mut self, material: &Material, order: [u8; 2], file: usize) {
+        let encoding: &EncodingTables = encoding();
+        let has_pawns: bool = material.has_pawns();
+        let unique: bool = material.has_unique_pieces();
+
+        // Leading group is the leading pawns, or the kings and a unique piece
+        let mut first_len: i32 = if has_pawns {
+            0
+        } else if unique {
+            3
+        } else {
+            2
+        };
+        let mut n: usize = 0;
+        self.group_len[0] = 1;
+        for i in 1..material.piece_count() {
+            first_len -= 1;
+            if first_len > 0 || self.pieces[i] == self.pieces[i - 1] {
+                self.group_len[n] += 1;
+            } else {
+                n += 1;
+                self.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        self.group_len[n] = 0;
+
+        // Groups are encoded in the table's order, remaining pawns second if both sides have some
+        let pawns_on_both_sides: bool = has_pawns && material.pawn_counts()[1] > 0;
+        let mut next: usize = if pawns_on_both_sides { 2 } else { 1 };
+        let mut free_squares: usize = 64
+            - self.group_len[0]
+            - if pawns_on_both_sides {
+                self.group_len[1]
+            } else {
+                0
+            };
+        let mut idx: u64 = 1;
+        let mut k: usize = 0;
+        while (next < n || k == order[0] as usize || k == order[1] as usize) && k < 16 {
+            if k == order[0] as usize {
+                self.group_idx[0] = idx;
+                idx *= if has_pawns {
+                    encoding.lead_pawns_size[self.group_len[0]][file]
+                } else if unique {
+                    31_332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                self.group_idx[1] = idx;
+                idx *= encoding.binomial[self.group_len[1]][48 - self.group_len[0]];
+            } else if next < n {
+                self.group_idx[next] = idx;
+                idx *= encoding.binomial[self.group_len[next]][free_squares];
+                free_squares -= self.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        self.group_idx[n] = idx;
+    }
+}
+
+/// Table files of one material, read on first use
+struct TableFiles {
+    material: Material,
+    wdl_path: Option<PathBuf>,
+    dtz_path: Option<PathBuf>,
+    wdl: OnceLock<Option<Table>>,
+    dtz: OnceLock<Option<Table>>,
+}
+
+impl TableFiles {
+    fn table(&self, kind: TableKind) -> Option<&Table> {
+        let (path, cell): (&Option<PathBuf>, &OnceLock<Option<Table>>) = match kind {
+            TableKind::Wdl => (&self.wdl_path, &self.wdl),
+            TableKind::Dtz => (&self.dtz_path, &self.dtz),
+        };
+        cell.get_or_init(|| {
+            let bytes: Vec<u8> = std::fs::read(path.as_ref()?).ok()?;
+            Table::parse(bytes, kind, self.material)
+        })
+        .as_ref()
+    }
+}
+
+/// Syzygy endgame tablebases, the result of positions with few pieces with perfect play<br />
+/// Tables are read from disk when first probed<br />
+/// https://www.chessprogramming.org/Syzygy_Bases
+pub struct Tablebase {
+    /// Table files by material name, e.g. `KRvK`
+    tables: HashMap<String, TableFiles>,
+    max_pieces: usize,
+}
+
+impl std::fmt::Debug for Tablebase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Tablebase")
+            .field("tables", &self.tables.len())
+            .field("max_pieces", &self.max_pieces)
+            .finish()
+    }
+}
+
+impl Tablebase {
+    /// Finds the `.rtbw` WDL and `.rtbz` DTZ files in the directories,
+    /// separated like in the `PATH` environment variable
+    pub fn open(paths: impl AsRef<OsStr>) -> std::io::Result<Tablebase> {
+        let mut tables: HashMap<String, TableFiles> = HashMap::new();
+        for directory in std::env::split_paths(&paths) {
+            for entry in std::fs::read_dir(directory)? {
+                let path: PathBuf = entry?.path();
+                let Some(material) = path
+                    .file_stem()
+                    .and_then(OsStr::to_str)
+                    .and_then(Material::from_name)
+                else {
+                    continue;
+                };
+                let is_wdl: bool = match path.extension().and_then(OsStr::to_str) {
+                    Some("rtbw") => true,
+                    Some("rtbz") => false,
+                    _ => continue,
+                };
+
+                let files: &mut TableFiles =
+                    tables.entry(material.name()).or_insert_with(|| TableFiles {
+                        material,
+                        wdl_path: None,
+                        dtz_path: None,
+                        wdl: OnceLock::new(),
+                        dtz: OnceLock::new(),
+                    });
+                if is_wdl {
+                    files.wdl_path = Some(path);
+                } else {
+                    files.dtz_path = Some(path);
+                }
+            }
+        }
+
+        let max_pieces: usize = tables
+            .values()
+            .filter(|files| files.wdl_path.is_some())
+            .map(|files| files.material.piece_count())
+            .max()
+            .unwrap_or(0);
+        Ok(Tablebase { tables, max_pieces })
+    }
+
+    /// Number of materials with a WDL table
+    pub fn len(&self) -> usize {
+        self.tables
+            .values()
+            .filter(|files| files.wdl_path.is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Most pieces of the tables found, kings included
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Returns true if the position has few enough pieces and no castling rights, tables don't store any
+    pub fn can_probe(&self, position: &Position) -> bool {
+        let rights: CastlingRights = position.castling_rights;
+        (position.board.occupied().count_ones() as usize) <= self.max_pieces
+            && !(rights.white_king_side
+                || rights.white_queen_side
+                || rights.black_king_side
+                || rights.black_queen_side)
+    }
+
+    /// Returns the result of the position with perfect play, None if it's not in the tables
+    pub fn probe_wdl(&self, position: &Position) -> Option<Wdl> {
+        if !self.can_probe(position) {
+            return None;
+        }
+        self.search(position, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Returns the number of plies to the next capture or pawn move with perfect play,
+    /// positive if the side to move wins, negative if it loses and 0 for draws<br />
+    /// Cursed wins and blessed losses count 100 more plies<br />
+    /// None if it's not in the tables
+    pub fn probe_dtz(&self, position: &Position) -> Option<i32> {
+        if !self.can_probe(position) {
+            return None;
+        }
+        self.dtz(position)
+    }
+
+    /// Ranks the legal moves of the position by their result, None if it's not in the tables<br />
+    /// Wins within the fifty-move counter rank 1000, slower wins and all losses are ranked by DTZ
+    pub fn probe_root(&self, position: &Position) -> Option<Vec<RootMove>> {
+        if !self.can_probe(position) {
+            return None;
+        }
+
+        let halfmove_clock: i32 = position.halfmove_clock as i32;
+        let mut root_moves: Vec<RootMove> = Vec::new();
+        for mv in generate_legal_moves(position) {
+            let next: Position = position.make_move(mv);
+            let mut dtz: i32 = if next.halfmove_clock == 0 {
+                dtz_before_zeroing(-self.search(&next, false)?.0)
+            } else {
+                let dtz: i32 = -self.dtz(&next)?;
+                dtz + dtz.signum()
+            };
+
+            // Mating move wins at once
+            if dtz == 2
+                && next.is_in_check(next.color_to_move)
+                && generate_legal_moves(&next).is_empty()
+            {
+                dtz = 1;
+            }
+
+            let rank: i32 = if dtz > 0 {
+                if dtz + halfmove_clock <= 99 {
+                    1000
+                } else {
+                    1000 - (dtz + halfmove_clock)
+                }
+            } else if dtz < 0 {
+                if -dtz * 2 + halfmove_clock < 100 {
+                    -1000
+                } else {
+                    -1000 + (-dtz + halfmove_clock)
+                }
+            } else {
+                0
+            };
+            root_moves.push(RootMove { mv, dtz, rank });
+        }
+
+        Some(root_moves)
+    }
+
+    /// Finds the table of the position's material, and whether black has the first side's pieces
+    fn table(&self, position: &Position, kind: TableKind) -> Option<(&Table, bool)> {
+        let material: Material = Material::from_position(position);
+        if let Some(files) = self.tables.get(&material.name()) {
+            return Some((files.table(kind)?, false));
+        }
+        let files: &TableFiles = self.tables.get(&material.flipped().name())?;
+        Some((files.table(kind)?, true))
+    }
+
+    fn probe_table(&self, position: &Position, kind: TableKind, wdl: Wdl) -> Option<TableValue> {
+        // Bare kings are a draw without a table
+        if position.board.occupied().count_ones() == 2 {
+            return Some(TableValue::Value(0));
+        }
+        let (table, black_stronger): (&Table, bool) = self.table(position, kind)?;
+        Some(table.probe(position, black_stronger, wdl))
+    }
+
+    /// Probes the WDL table and the captures, the tables may store any value when a capture
+    /// is at least as good, to compress better<br />
+    /// With `zeroing_moves` pawn moves are tried too, as DTZ tables don't store those positions<br />
+    /// Also returns true if a capture or pawn move is the best move
+    fn search(&self, position: &Position, zeroing_moves: bool) -> Option<(Wdl, bool)> {
+        let moves: Vec<Move> = generate_legal_moves(position);
+        let mut best: Wdl = Wdl::Loss;
+        let mut move_count: usize = 0;
+
+        for mv in &moves {
+            if !is_capture(position, *mv)
+                && (!zeroing_moves || !position.board.piece_at(mv.from).is_pawn())
+            {
+                continue;
+            }
+
+            move_count += 1;
+            let value: Wdl = -self.search(&position.make_move(*mv), false)?.0;
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // Tables don't know en passant, with only zeroing moves the searched result is exact
+        let no_more_moves: bool = move_count > 0 && move_count == moves.len();
+        let value: Wdl = if no_more_moves {
+            best
+        } else {
+            match self.probe_table(position, TableKind::Wdl, Wdl::Draw)? {
+                TableValue::Value(value) => Wdl::from_value(value),
+                TableValue::ChangeStm => return None,
+            }
+        };
+
+        if best >= value {
+            return Some((best, best > Wdl::Draw || no_more_moves));
+        }
+        Some((value, false))
+    }
+
+    fn dtz(&self, position: &Position) -> Option<i32> {
+        let (wdl, zeroing_best_move): (Wdl, bool) = self.search(position, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_best_move {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        let sign: i32 = wdl.value().signum();
+        match self.probe_table(position, TableKind::Dtz, wdl)? {
+            TableValue::Value(dtz) => {
+                let cursed: bool = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+                Some((dtz + if cursed { 100 } else { 0 }) * sign)
+            }
+            // Table stores the other side to move, the best move's DTZ is one more
+            TableValue::ChangeStm => {
+                let mut min_dtz: i32 = 0xFFFF;
+                for mv in generate_legal_moves(position) {
+                    let zeroing: bool =
+                        is_capture(position, mv) || position.board.piece_at(mv.from).is_pawn();
+                    let next: Position = position.make_move(mv);
+                    let mut dtz: i32 = if zeroing {
+                        -dtz_before_zeroing(self.search(&next, false)?.0)
+                    } else {
+                        -self.dtz(&next)?
+                    };
+
+                    if dtz == 1
+                        && next.is_in_check(next.color_to_move)
+                        && generate_legal_moves(&next).is_empty()
+                    {
+                        min_dtz = 1;
+                    }
+                    if !zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz < min_dtz && dtz.signum() == sign {
+                        min_dtz = dtz;
+                    }
+                }
+
+                // No legal move, the side to move is mated
+                Some(if min_dtz == 0xFFFF { -1 } else { min_dtz })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_encoding_tables() {
+        let encoding: &EncodingTables = encoding();
+        let mut kings: Vec<u64> = encoding
+            .map_kk
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, squares)| {
+                (0..64).filter_map(move |second| {
+                    let first: usize = (0..=27)
+                        .find(|square| {
+                            encoding.map_a1d1d4[*square] == idx && (idx != 0 || *square == 1)
+                        })
+                        .unwrap();
+                    let touching: Bitboard = king_attacks(Square::from_index(first))
+                        | Square::from_index(first).bitboard();
+                    let above: bool = off_diagonal(first) == 0 && off_diagonal(second) > 0;
+                    (touching & (1 << second) == 0 && !above).then_some(squares[second])
+                })
+            })
+            .collect();
+        kings.sort();
+        assert_eq!(kings, (0..462).collect::<Vec<u64>>());
+
+        let mut pawns: Vec<usize> = (8..56).map(|square| encoding.map_pawns[square]).collect();
+        pawns.sort();
+        assert_eq!(pawns, (0..48).collect::<Vec<usize>>());
+        assert_eq!(encoding.map_pawns[8], 47);
+
+        assert_eq!(encoding.binomial[2][5], 10);
+        assert_eq!(encoding.binomial[5][63], 7_028_847);
+        assert_eq!(encoding.lead_pawns_size[1].iter().sum::<u64>(), 24);
+    }
+
+    #[test]
+    fn names_material() {
+        let material: Material = Material::from_name("KRPvKN").unwrap();
+        assert_eq!(material.name(), "KRPvKN");
+        assert_eq!(material.flipped().name(), "KNvKRP");
+        assert_eq!(material.piece_count(), 5);
+        assert!(material.has_pawns() && material.has_unique_pieces());
+        assert_eq!(material.pawn_counts(), [1, 0]);
+        assert_eq!(Material::from_name("KRRvKR").unwrap().name(), "KRRvKR");
+        assert!(!Material::from_name("KRRvKRR").unwrap().has_unique_pieces());
+        assert_eq!(Material::from_name("KPvKPP").unwrap().pawn_counts(), [1, 2]);
+        assert_eq!(Material::from_name("KRvR"), None);
+        assert_eq!(Material::from_name("KRK"), None);
+
+        let position: Position = Position::from_fen("8/8/4k3/8/2n5/8/1P6/4K2R w - - 0 1").unwrap();
+        assert_eq!(Material::from_position(&position).name(), "KRPvKN");
+    }
+
+    /// WDL table of one material that stores the same value everywhere,
+    /// from the point of view of white to move and the opposite for black to move
+    fn single_value_table(material: &str, value: Wdl) -> Vec<u8> {
+        let material: Material = Material::from_name(material).unwrap();
+        let mut bytes: Vec<u8> = WDL_MAGIC.to_vec();
+        bytes.push(!material.is_symmetric() as u8);
+        bytes.push(0);
+
+        let codes: Vec<u8> = [PieceColor::White, PieceColor::Black]
+            .iter()
+            .flat_map(|color| {
+                (0..6).rev().flat_map(move |kind| {
+                    std::iter::repeat_n(
+                        piece_code(PieceType::from_kind_index(kind, *color)),
+                        material.counts[color.index()][kind] as usize,
+                    )
+                })
+            })
+            .collect();
+        bytes.extend(codes.iter().map(|code| code | code << 4));
+        if bytes.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        for value in [value, -value] {
+            bytes.extend([FLAG_SINGLE_VALUE, (value.value() + 2) as u8]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn probes_tables() {
+        let directory: PathBuf =
+            std::env::temp_dir().join(format!("syzygy-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join("KQvK.rtbw"),
+            single_value_table("KQvK", Wdl::Win),
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("KNvK.rtbw"),
+            single_value_table("KNvK", Wdl::Draw),
+        )
+        .unwrap();
+        std::fs::write(directory.join("README.txt"), "not a table").unwrap();
+
+        let tablebase: Tablebase = Tablebase::open(&directory).unwrap();
+        assert_eq!(tablebase.len(), 2);
+        assert_eq!(tablebase.max_pieces(), 3);
+
+        let probe = |fen: &str| tablebase.probe_wdl(&Position::from_fen(fen).unwrap());
+        assert_eq!(probe("8/8/8/2k5/8/8/8/3QK3 w - - 0 1"), Some(Wdl::Win));
+        // Same table with colors swapped, from the weaker side's point of view
+        assert_eq!(probe("2q1k3/8/8/8/3K4/8/8/8 w - - 0 1"), Some(Wdl::Loss));
+        // King takes the queen
+        assert_eq!(probe("8/8/8/8/8/8/3k4/3Q3K b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe("8/8/8/3k4/8/8/8/3NK3 b - - 0 1"), Some(Wdl::Draw));
+        // Missing table and too many pieces
+        assert_eq!(probe("8/8/8/2k5/8/8/8/3RK3 w - - 0 1"), None);
+        assert_eq!(probe("8/8/8/2k5/8/8/8/3QK2R w K - 0 1"), None);
+
+        let position: Position = Position::from_fen("8/8/8/3k4/8/8/8/3NK3 w - - 0 1").unwrap();
+        assert_eq!(tablebase.probe_dtz(&position), Some(0));
+        let root_moves: Vec<RootMove> = tablebase.probe_root(&position).unwrap();
+        assert_eq!(root_moves.len(), generate_legal_moves(&position).len());
+        assert!(root_moves.iter().all(|root_move| root_move.rank == 0));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// Synthetic KRvK tables written by `tests/syzygy/generate_krvk.py`, not the published ones<br />
+    /// They follow the same compressed format but were never checked against real Syzygy files,
+    /// so this only shows the reader decodes what the generator meant to write
+    #[test]
+    fn probes_synthetic_compressed_tables() {
+        let directory: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "syzygy"]
+            .iter()
+            .collect();
+        let tablebase: Tablebase = Tablebase::open(&directory).unwrap();
+        assert_eq!(tablebase.len(), 1);
+
+        let probe = |fen: &str| -> (Wdl, i32) {
+            let position: Position = Position::from_fen(fen).unwrap();
+            (
+                tablebase.probe_wdl(&position).unwrap(),
+                tablebase.probe_dtz(&position).unwrap(),
+            )
+        };
+        // Ra8 mates
+        assert_eq!(probe("6k1/8/6K1/8/8/8/8/R7 w - - 0 1"), (Wdl::Win, 1));
+        // Longest win takes 16 moves
+        assert_eq!(probe("K7/8/8/8/8/4k3/8/6R1 w - - 0 1"), (Wdl::Win, 31));
+        assert_eq!(probe("8/k7/8/8/8/2R5/6K1/8 w - - 0 1"), (Wdl::Win, 19));
+
+        // The table stores DTZ for white to move only
+        assert_eq!(probe("R5k1/8/6K1/8/8/8/8/8 b - - 0 1"), (Wdl::Loss, -1));
+        assert_eq!(probe("7k/R7/6K1/8/8/8/8/8 b - - 0 1"), (Wdl::Loss, -2));
+        // Stalemate and king takes the rook
+        assert_eq!(probe("k7/8/K7/8/8/8/8/1R6 b - - 0 1"), (Wdl::Draw, 0));
+        assert_eq!(probe("8/8/8/4k3/4R3/8/8/K7 b - - 0 1"), (Wdl::Draw, 0));
+
+        // Colors swapped, the board is flipped to look them up
+        assert_eq!(probe("r7/8/8/8/8/6k1/8/6K1 b - - 0 1"), (Wdl::Win, 1));
+        assert_eq!(probe("6r1/8/4K3/8/8/8/8/k7 b - - 0 1"), (Wdl::Win, 31));
+        assert_eq!(probe("8/8/8/8/8/6k1/r7/7K w - - 0 1"), (Wdl::Loss, -2));
+    }
+}
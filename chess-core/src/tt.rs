@@ -0,0 +1,246 @@
+use crate::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Size of the transposition table when none is given, in MB
+pub const DEFAULT_HASH_SIZE_MB: usize = 16;
+
+/// How the stored score relates to the real score of the position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    Exact,
+    /// Real score is at least the stored one, the search failed high
+    Lower,
+    /// Real score is at most the stored one, no move raised alpha
+    Upper,
+}
+
+/// Search result of one position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TtEntry {
+    pub hash: u64,
+    pub best_move: Option<Move>,
+    /// Mate scores are stored relative to the position, not to the root
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+impl TtEntry {
+    /// Packs everything but the hash into 64 bits, never 0 as the bound takes 1 to 3
+    fn to_data(self) -> u64 {
+        let mv: u64 = match self.best_move {
+            Some(mv) => {
+                let square = |square: Square| (square.y * 8 + square.x) as u64;
+                let promotion: u64 = mv.promotion.map_or(0, |promotion| {
+                    (promotion.kind_index() as u64 + 1)
+                        | (promotion.color().map_or(0, |color| color.index() as u64)) << 3
+                });
+                1 << 16 | promotion << 12 | square(mv.to) << 6 | square(mv.from)
+            }
+            None => 0,
+        };
+        let bound: u64 = match self.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        (self.score as u32 as u64) << 32 | bound << 25 | (self.depth as u64) << 17 | mv
+    }
+
+    fn from_data(hash: u64, data: u64) -> Self {
+        let square = |bits: u64| Square {
+            x: (bits & 7) as u8,
+            y: (bits >> 3 & 7) as u8,
+        };
+        let best_move: Option<Move> = (data >> 16 & 1 == 1).then(|| Move {
+            from: square(data),
+            to: square(data >> 6),
+            promotion: match data >> 12 & 7 {
+                0 => None,
+                kind => Some(PieceType::from_kind_index(
+                    kind as usize - 1,
+                    if data >> 15 & 1 == 1 {
+                        PieceColor::Black
+                    } else {
+                        PieceColor::White
+                    },
+                )),
+            },
+        });
+
+        TtEntry {
+            hash,
+            best_move,
+            score: (data >> 32) as u32 as i32,
+            depth: (data >> 17) as u8,
+            bound: match data >> 25 & 3 {
+                1 => Bound::Exact,
+                2 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+        }
+    }
+}
+
+/// Entry stored as its packed data and the hash xor the data,
+/// so an entry torn by writes of two threads doesn't match any hash
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Fixed-size table of search results indexed by Zobrist hash<br />
+/// Lock-free, threads of the search read and write it at the same time<br />
+/// https://www.chessprogramming.org/Transposition_Table<br />
+/// https://www.chessprogramming.org/Shared_Hash_Table#Lockless
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl TranspositionTable {
+    /// Creates a table taking about `size_mb` MB, at least one entry
+    pub fn new(size_mb: usize) -> Self {
+        let n_of_entries: usize = (size_mb * 1024 * 1024 / std::mem::size_of::<Slot>()).max(1);
+        Self {
+            slots: std::iter::repeat_with(Slot::default)
+                .take(n_of_entries)
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots
+            .iter()
+            .all(|slot| slot.data.load(Ordering::Relaxed) == 0)
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the entry of the position, if it's still in the table
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        let slot: &Slot = &self.slots[self.index(hash)];
+        let data: u64 = slot.data.load(Ordering::Relaxed);
+        if data == 0 || slot.key.load(Ordering::Relaxed) ^ data != hash {
+            return None;
+        }
+        Some(TtEntry::from_data(hash, data))
+    }
+
+    /// Stores the entry, replacing other positions always and the same position
+    /// only with a search at least as deep
+    pub fn store(&self, entry: TtEntry) {
+        let slot: &Slot = &self.slots[self.index(entry.hash)];
+        let replace: bool = match self.probe(entry.hash) {
+            Some(old) => entry.depth >= old.depth,
+            None => true,
+        };
+        if replace {
+            let data: u64 = entry.to_data();
+            slot.key.store(entry.hash ^ data, Ordering::Relaxed);
+            slot.data.store(data, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (hash % self.slots.len() as u64) as usize
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_SIZE_MB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_and_replaces_entries() {
+        let tt: TranspositionTable = TranspositionTable::new(1);
+        assert!(tt.len() > 1_000);
+        assert!(tt.is_empty());
+
+        let entry: TtEntry = TtEntry {
+            hash: 12345,
+            best_move: None,
+            score: 10,
+            depth: 4,
+            bound: Bound::Exact,
+        };
+        tt.store(entry);
+        assert_eq!(tt.probe(12345), Some(entry));
+        assert_eq!(tt.probe(12345 + tt.len() as u64), None);
+
+        // Shallower search of the same position doesn't replace a deeper one
+        tt.store(TtEntry { depth: 2, ..entry });
+        assert_eq!(tt.probe(12345).map(|entry| entry.depth), Some(4));
+
+        // Other position with the same index does
+        let other: u64 = 12345 + tt.len() as u64;
+        tt.store(TtEntry {
+            hash: other,
+            depth: 1,
+            ..entry
+        });
+        assert_eq!(tt.probe(12345), None);
+        assert_eq!(tt.probe(other).map(|entry| entry.depth), Some(1));
+
+        tt.clear();
+        assert!(tt.is_empty());
+    }
+
+    #[test]
+    fn packs_entries() {
+        let position: Position = Position::from_fen("4k3/1P6/8/8/8/8/6p1/4K3 b - - 0 1").unwrap();
+        for mv in generate_legal_moves(&position) {
+            let entry: TtEntry = TtEntry {
+                hash: 42,
+                best_move: Some(mv),
+                score: -MATE_SCORE + 3,
+                depth: 255,
+                bound: Bound::Upper,
+            };
+            assert_eq!(TtEntry::from_data(42, entry.to_data()), entry);
+        }
+    }
+
+    #[test]
+    fn shares_table_between_threads() {
+        let tt: TranspositionTable = TranspositionTable::new(1);
+        std::thread::scope(|scope| {
+            for thread in 0..4u64 {
+                let tt: &TranspositionTable = &tt;
+                scope.spawn(move || {
+                    for hash in (thread * 1_000..(thread + 1) * 1_000).map(|hash| hash * 7919) {
+                        tt.store(TtEntry {
+                            hash,
+                            best_move: None,
+                            score: hash as i32,
+                            depth: 1,
+                            bound: Bound::Exact,
+                        });
+                    }
+                });
+            }
+        });
+
+        // Every entry read back is whole, never mixed from two writes
+        for hash in (0..4_000u64).map(|hash| hash * 7919) {
+            if let Some(entry) = tt.probe(hash) {
+                assert_eq!(entry.score, hash as i32);
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::*;
+
+impl Move {
+    /// Returns the move in UCI's long algebraic notation, e.g. `e2e4`, `e1g1` or `e7e8q`
+    pub fn to_uci(&self) -> String {
+        let promotion: String = self
+            .promotion
+            .and_then(piece_type_to_fen)
+            .map(|c| c.to_ascii_lowercase().to_string())
+            .unwrap_or_default();
+        format!("{}{}{}", self.from, self.to, promotion)
+    }
+}
+
+/// Resolves a move in UCI's long algebraic notation against the legal moves of the position<br />
+/// Returns None if it isn't a legal move
+pub fn uci_to_move(position: &Position, uci: &str) -> Option<Move> {
+    let from: Square = uci.get(0..2)?.parse().ok()?;
+    let to: Square = uci.get(2..4)?.parse().ok()?;
+    let promotion: Option<PieceType> = match uci.get(4..) {
+        Some("") | None => None,
+        Some(c) => Some(
+            piece_type_from_fen(c.chars().next()?.to_ascii_uppercase())?
+                .with_color(position.color_to_move),
+        ),
+    };
+
+    generate_legal_moves(position)
+        .into_iter()
+        .find(|mv| mv.from == from && mv.to == to && mv.promotion == promotion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_uci_moves() {
+        let position: Position = Position::new();
+        let mv: Move = uci_to_move(&position, "e2e4").unwrap();
+        assert_eq!(mv.to_uci(), "e2e4");
+        assert_eq!(uci_to_move(&position, "e2e5"), None);
+        assert_eq!(uci_to_move(&position, "e2"), None);
+
+        let position: Position = Position::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mv: Move = uci_to_move(&position, "a7a8n").unwrap();
+        assert_eq!(mv.promotion, Some(PieceType::KnightWhite));
+        assert_eq!(mv.to_uci(), "a7a8n");
+        assert_eq!(uci_to_move(&position, "a7a8"), None);
+    }
+}
@@ -0,0 +1,293 @@
+#!/usr/bin/env python3
+"""Writes the synthetic KRvK.rtbw and KRvK.rtbz test fixtures.
+
+Solves KRvK by retrograde analysis and writes the result in the Syzygy table
+format (https://github.com/syzygy1/tb), Huffman coded with recursive pairing.
+These are NOT the published Syzygy tables, they only follow the same format.
+
+Usage: python3 generate_krvk.py <directory>
+"""
+import struct, sys, heapq
+from collections import Counter
+
+def sq(f, r): return r * 8 + f
+def fl(s): return s % 8
+def rk(s): return s // 8
+def adj(a, b): return a != b and abs(fl(a) - fl(b)) <= 1 and abs(rk(a) - rk(b)) <= 1
+KING = [[t for t in range(64) if adj(s, t)] for s in range(64)]
+
+def rook_ray(frm, blockers):
+    out = []
+    for df, dr in ((1, 0), (-1, 0), (0, 1), (0, -1)):
+        f, r = fl(frm) + df, rk(frm) + dr
+        while 0 <= f < 8 and 0 <= r < 8:
+            t = sq(f, r)
+            out.append(t)
+            if t in blockers: break
+            f += df; r += dr
+    return out
+
+def rook_attacks(frm, target, blockers):
+    return target in rook_ray(frm, blockers)
+
+# ---------- solve ----------
+# Positions are (white king, white rook, black king) squares
+# W: plies to mate with white to move, BSTATE: plies to mate or 'draw' with black to move
+W = {}
+legalW = []; legalB = []
+for wk in range(64):
+    for wr in range(64):
+        if wr == wk: continue
+        for bk in range(64):
+            if bk in (wk, wr) or adj(wk, bk): continue
+            key = (wk, wr, bk)
+            in_check = rook_attacks(wr, bk, {wk})
+            legalB.append(key)
+            if not in_check: legalW.append(key)
+
+def white_moves(key):
+    wk, wr, bk = key
+    res = []
+    for t in KING[wk]:
+        if t != wr and not adj(t, bk): res.append((t, wr, bk))
+    for t in rook_ray(wr, {wk, bk}):
+        if t not in (wk, bk): res.append((wk, t, bk))
+    return res
+
+def black_moves(key):
+    """Returns (successors, has_capture)"""
+    wk, wr, bk = key
+    res = []; cap = False
+    for t in KING[bk]:
+        if adj(t, wk): continue
+        if t == wr:
+            cap = True; continue
+        if rook_attacks(wr, t, {wk}): continue
+        res.append((wk, wr, t))
+    return res, cap
+
+WS = {k: white_moves(k) for k in legalW}
+BS = {}
+BSTATE = {}
+for k in legalB:
+    succ, cap = black_moves(k)
+    BS[k] = succ
+    wk, wr, bk = k
+    if cap: BSTATE[k] = 'draw'
+    elif not succ:
+        BSTATE[k] = 0 if rook_attacks(wr, bk, {wk}) else 'draw'
+
+n = 1
+while True:
+    changed = False
+    for k in legalW:
+        if k in W: continue
+        if any(BSTATE.get(s) == n - 1 for s in WS[k]):
+            W[k] = n; changed = True
+    for k in legalB:
+        if k in BSTATE: continue
+        if all(s in W for s in BS[k]):
+            BSTATE[k] = 1 + max(W[s] for s in BS[k]); changed = True
+    if not changed: break
+    n += 2
+print('max white dtz', max(W.values()), 'white draws', sum(1 for k in legalW if k not in W))
+
+# ---------- encoding ----------
+def off(s): return rk(s) - fl(s)
+MapB1H1H7 = [0] * 64; c = 0
+for s in range(64):
+    if off(s) < 0: MapB1H1H7[s] = c; c += 1
+MapA1D1D4 = [0] * 64; c = 0; diag = []
+for s in range(28):
+    if fl(s) > 3: continue
+    if off(s) < 0: MapA1D1D4[s] = c; c += 1
+    elif off(s) == 0: diag.append(s)
+for s in diag: MapA1D1D4[s] = c; c += 1
+SIZE = 31332
+
+def encode(sqs):
+    sqs = list(sqs)
+    if fl(sqs[0]) > 3: sqs = [s ^ 7 for s in sqs]
+    if rk(sqs[0]) > 3: sqs = [s ^ 56 for s in sqs]
+    for i in range(3):
+        if off(sqs[i]) == 0: continue
+        if off(sqs[i]) > 0:
+            sqs = sqs[:i] + [((s >> 3) | (s << 3)) & 63 for s in sqs[i:]]
+        break
+    s0, s1, s2 = sqs
+    a1 = int(s1 > s0); a2 = int(s2 > s0) + int(s2 > s1)
+    if off(s0):
+        return (MapA1D1D4[s0] * 63 + (s1 - a1)) * 62 + s2 - a2
+    if off(s1):
+        return (6 * 63 + rk(s0) * 28 + MapB1H1H7[s1]) * 62 + s2 - a2
+    if off(s2):
+        return 6 * 63 * 62 + 4 * 28 * 62 + rk(s0) * 7 * 28 + (rk(s1) - a1) * 28 + MapB1H1H7[s2]
+    return 6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rk(s0) * 7 * 6 + (rk(s1) - a1) * 6 + (rk(s2) - a2)
+
+# Table piece order: white king, white rook, black king
+def order(key):
+    wk, wr, bk = key
+    return (wk, wr, bk)
+
+def fill(entries):
+    vals = [None] * SIZE
+    for idx, v in entries:
+        assert vals[idx] is None or vals[idx] == v, (idx, vals[idx], v)
+        vals[idx] = v
+    # Unreachable indices take the previous value
+    last = next(v for v in vals if v is not None)
+    out = []
+    for v in vals:
+        if v is None: v = last
+        out.append(v); last = v
+    return out
+
+wdl_w = fill((encode(order(k)), 4 if k in W else 2) for k in legalW)
+wdl_b = fill((encode(order(k)), 0 if isinstance(BSTATE.get(k), int) else 2) for k in legalB)
+dtz_w = fill((encode(order(k)), W[k] - 1) for k in legalW if k in W)
+
+# ---------- compression ----------
+def compress(values, max_syms=1024):
+    """Returns (symbols as (left, right) pairs, sequence of symbols, values per symbol minus one)"""
+    leaves = sorted(set(values))
+    syms = [(v, 0xFFF) for v in leaves]
+    symlen = [0] * len(syms)
+    idx = {v: i for i, v in enumerate(leaves)}
+    seq = [idx[v] for v in values]
+    if len(syms) == 1:
+        # Huffman needs two symbols
+        syms.append((leaves[0], 0xFFF)); symlen.append(0)
+    while len(syms) < max_syms:
+        cnt = Counter()
+        i = 0
+        prev = None
+        for i in range(len(seq) - 1):
+            p = (seq[i], seq[i + 1])
+            if p == prev: prev = None; continue  # don't count overlapping runs twice
+            cnt[p] += 1; prev = p
+        best = None
+        for p, cn in cnt.most_common():
+            if symlen[p[0]] + symlen[p[1]] + 1 <= 255:
+                best = (p, cn); break
+        if best is None or best[1] < 4: break
+        (a, b), _ = best
+        new = len(syms)
+        syms.append((a, b)); symlen.append(symlen[a] + symlen[b] + 1)
+        out = []; i = 0
+        while i < len(seq):
+            if i + 1 < len(seq) and seq[i] == a and seq[i + 1] == b:
+                out.append(new); i += 2
+            else:
+                out.append(seq[i]); i += 1
+        seq = out
+    return syms, seq, symlen
+
+def huffman_lengths(freq):
+    n = len(freq)
+    heap = [(f if f else 0, i, [i]) for i, f in enumerate(freq)]
+    heapq.heapify(heap)
+    lengths = [0] * n
+    cnt = n
+    while len(heap) > 1:
+        f1, _, a = heapq.heappop(heap)
+        f2, _, b = heapq.heappop(heap)
+        for s in a + b: lengths[s] += 1
+        heapq.heappush(heap, (f1 + f2, cnt, a + b)); cnt += 1
+    return lengths
+
+def build_item(values, flags, block_log=6, span_log=8):
+    syms, seq, symlen = compress(values)
+    freq = Counter(seq)
+    lengths = huffman_lengths([freq.get(i, 0) for i in range(len(syms))])
+    minL, maxL = min(lengths), max(lengths)
+    assert maxL <= 32
+    # Renumber symbols: longer codes first
+    new_order = sorted(range(len(syms)), key=lambda s: (-lengths[s], s))
+    renum = {old: new for new, old in enumerate(new_order)}
+    nsyms = len(syms)
+    btree = []
+    for old in new_order:
+        l, r = syms[old]
+        if r == 0xFFF: btree.append((l, 0xFFF))
+        else: btree.append((renum[l], renum[r]))
+    new_len = [lengths[o] for o in new_order]
+    new_symlen = [symlen[o] for o in new_order]
+    seq = [renum[s] for s in seq]
+    nl = maxL - minL + 1
+    count = [0] * nl
+    for L in new_len: count[L - minL] += 1
+    lowest = [0] * nl
+    for i in range(nl - 2, -1, -1): lowest[i] = lowest[i + 1] + count[i + 1]
+    base = [0] * nl
+    for i in range(nl - 2, -1, -1):
+        assert (base[i + 1] + count[i + 1]) % 2 == 0
+        base[i] = (base[i + 1] + count[i + 1]) // 2
+    assert base[0] + count[0] == 1 << minL
+    code = {}
+    for s in range(nsyms):
+        i = new_len[s] - minL
+        code[s] = (base[i] + s - lowest[i], new_len[s])
+    # Blocks
+    block_bytes = 1 << block_log
+    blocks = []; cur = []; bits = 0; nvals = 0
+    for s in seq:
+        c, L = code[s]
+        if bits + L > block_bytes * 8 or nvals + new_symlen[s] + 1 > 65536:
+            blocks.append((cur, nvals)); cur = []; bits = 0; nvals = 0
+        cur.append(s); bits += L; nvals += new_symlen[s] + 1
+    blocks.append((cur, nvals))
+    data = bytearray()
+    for syms_in_block, _ in blocks:
+        acc = 0; nb = 0
+        for s in syms_in_block:
+            c, L = code[s]; acc = (acc << L) | c; nb += L
+        acc <<= block_bytes * 8 - nb
+        data += acc.to_bytes(block_bytes, 'big')
+    starts = []; t = 0
+    for _, nv in blocks: starts.append(t); t += nv
+    assert t == len(values)
+    span = 1 << span_log
+    sparse = bytearray()
+    nsparse = (len(values) + span - 1) // span
+    for k in range(nsparse):
+        p = k * span + span // 2
+        b = len(blocks) - 1
+        for j in range(len(blocks)):
+            if starts[j] + blocks[j][1] > p: b = j; break
+        o = p - starts[b]
+        assert o < 65536
+        sparse += struct.pack('<IH', b, o)
+    blen = bytearray()
+    for _, nv in blocks: blen += struct.pack('<H', nv - 1)
+    sizes = bytearray([flags, block_log, span_log, 0])
+    sizes += struct.pack('<I', len(blocks))
+    sizes += bytes([maxL, minL])
+    for i in range(nl): sizes += struct.pack('<H', lowest[i])
+    sizes += struct.pack('<H', nsyms)
+    for l, r in btree:
+        sizes += bytes([l & 0xFF, ((l >> 8) & 0xF) | ((r & 0xF) << 4), r >> 4])
+    if nsyms & 1: sizes += b'\0'
+    return sizes, sparse, blen, data
+
+def write_table(path, magic, items):
+    out = bytearray(magic)
+    out.append(0x01)               # split, no pawns
+    out.append(0x00)               # order of the leading group for both sides
+    for code in (6, 4, 14):        # K, R, k
+        out.append(code | code << 4)
+    if len(out) & 1: out.append(0)
+    built = [build_item(*it) for it in items]
+    for sizes, *_ in built: out += sizes
+    if len(out) & 1: out.append(0)   # after the (empty) DTZ map
+    for _, sparse, _, _ in built: out += sparse
+    for _, _, blen, _ in built: out += blen
+    for *_, data in built:
+        while len(out) % 64: out.append(0)
+        out += data
+    open(path, 'wb').write(out)
+    print(path, len(out), 'bytes')
+
+d = sys.argv[1]
+write_table(d + '/KRvK.rtbw', bytes([0x71, 0xE8, 0x23, 0x5D]), [(wdl_w, 0), (wdl_b, 0)])
+# White to move stored, DTZ in plies
+write_table(d + '/KRvK.rtbz', bytes([0xD7, 0x66, 0x0C, 0xA5]), [(dtz_w, 4 | 8)])
@@ -0,0 +1,424 @@
+use chess_core::*;
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+/// How long the engine gets to answer `uci` and `isready`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Score the engine reported, from the side to move's point of view
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EngineScore {
+    Centipawns(i32),
+    /// Moves to mate, negative if the side to move gets mated
+    Mate(i32),
+}
+
+/// Search progress from an `info` line, fields the engine didn't send are None
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EngineInfo {
+    pub depth: Option<u32>,
+    pub score: Option<EngineScore>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    /// Principal variation in UCI notation
+    pub pv: Vec<String>,
+}
+
+impl EngineInfo {
+    /// Parses an `info` line, returns None for other lines and for `info string`
+    pub fn parse(line: &str) -> Option<EngineInfo> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.first() != Some(&"info") || tokens.get(1) == Some(&"string") {
+            return None;
+        }
+
+        let mut info: EngineInfo = EngineInfo::default();
+        let mut i: usize = 1;
+        while i < tokens.len() {
+            let next = |offset: usize| tokens.get(i + offset).copied().unwrap_or_default();
+            match tokens[i] {
+                "depth" => info.depth = next(1).parse().ok(),
+                "nodes" => info.nodes = next(1).parse().ok(),
+                "nps" => info.nps = next(1).parse().ok(),
+                "score" => {
+                    info.score = match next(1) {
+                        "cp" => next(2).parse().ok().map(EngineScore::Centipawns),
+                        "mate" => next(2).parse().ok().map(EngineScore::Mate),
+                        _ => None,
+                    };
+                    i += 1;
+                }
+                // Principal variation is always the last field
+                "pv" => {
+                    info.pv = tokens[i + 1..].iter().map(|mv| mv.to_string()).collect();
+                    break;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        Some(info)
+    }
+}
+
+/// What the engine reported since the last poll
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineEvent {
+    Info(EngineInfo),
+    /// None if the engine had no legal move or sent a move that isn't legal
+    BestMove(Option<Move>),
+}
+
+/// Chess engine running as a child process, talked to over UCI<br />
+/// The process gets `quit` and is killed when this is dropped
+pub struct ExternalEngine {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines of the engine's output, read on their own thread so polling never blocks
+    lines: Mutex<Receiver<String>>,
+    name: Option<String>,
+    /// Position the running search started from, to resolve its best move
+    position: Option<Position>,
+    /// Number of stopped searches whose best move hasn't arrived yet, those are thrown away
+    stale_best_moves: usize,
+}
+
+impl ExternalEngine {
+    /// Starts the engine and waits until it's ready
+    pub fn spawn(path: &Path) -> io::Result<ExternalEngine> {
+        let mut child: Child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::other("engine's stdin or stdout isn't piped"));
+        };
+
+        let (sender, receiver) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine: ExternalEngine = ExternalEngine {
+            child,
+            stdin,
+            lines: Mutex::new(receiver),
+            name: None,
+            position: None,
+            stale_best_moves: 0,
+        };
+
+        engine.send("uci")?;
+        for line in engine.wait_for("uciok")? {
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.to_string());
+            }
+        }
+        engine.is_ready()?;
+
+        Ok(engine)
+    }
+
+    /// Name the engine sent with `id name`
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {name} value {value}"))
+    }
+
+    /// Waits for the engine to answer `isready`
+    pub fn is_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        self.wait_for("readyok").map(|_| ())
+    }
+
+    /// Starts searching the position reached by the moves from the start position for `movetime`<br />
+    /// The best move comes later from `poll`
+    pub fn go(
+        &mut self,
+        start_position: &Position,
+        moves: &[Move],
+        movetime: Duration,
+    ) -> io::Result<()> {
+        let position: String = if *start_position == Position::new() {
+            "startpos".to_string()
+        } else {
+            format!("fen {}", start_position.to_fen())
+        };
+        let uci_moves: Vec<String> = moves.iter().map(Move::to_uci).collect();
+        let moves_field: String = if uci_moves.is_empty() {
+            String::new()
+        } else {
+            format!(" moves {}", uci_moves.join(" "))
+        };
+
+        self.send(&format!("position {position}{moves_field}"))?;
+        self.send(&format!("go movetime {}", movetime.as_millis()))?;
+
+        self.position = Some(moves.iter().fold(start_position.clone(), |position, mv| {
+            position.make_move(*mv)
+        }));
+        Ok(())
+    }
+
+    /// Stops the running search, its best move is never returned by `poll`
+    pub fn stop(&mut self) -> io::Result<()> {
+        if self.position.take().is_some() {
+            self.stale_best_moves += 1;
+        }
+        self.send("stop")
+    }
+
+    /// Returns true while a search started by `go` hasn't sent its best move
+    pub fn is_searching(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// Returns the next info or best move the engine sent, without waiting<br />
+    /// Err if the engine has exited
+    pub fn poll(&mut self) -> io::Result<Option<EngineEvent>> {
+        loop {
+            let line: String = {
+                let lines: MutexGuard<Receiver<String>> = self
+                    .lines
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                match lines.try_recv() {
+                    Ok(line) => line,
+                    Err(mpsc::TryRecvError::Empty) => return Ok(None),
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::BrokenPipe,
+                            "engine has exited",
+                        ))
+                    }
+                }
+            };
+
+            if let Some(best_move) = line.strip_prefix("bestmove") {
+                if self.stale_best_moves > 0 {
+                    self.stale_best_moves -= 1;
+                    continue;
+                }
+                let Some(position) = self.position.take() else {
+                    continue;
+                };
+
+                let best_move: Option<Move> = best_move
+                    .split_whitespace()
+                    .next()
+                    .and_then(|uci| uci_to_move(&position, uci));
+                return Ok(Some(EngineEvent::BestMove(best_move)));
+            }
+
+            if self.is_searching() {
+                if let Some(info) = EngineInfo::parse(&line) {
+                    return Ok(Some(EngineEvent::Info(info)));
+                }
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()
+    }
+
+    /// Returns the lines up to and including `expected`
+    fn wait_for(&mut self, expected: &str) -> io::Result<Vec<String>> {
+        let deadline: Instant = Instant::now() + HANDSHAKE_TIMEOUT;
+        let lines: MutexGuard<Receiver<String>> = self
+            .lines
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut received: Vec<String> = Vec::new();
+
+        loop {
+            let timeout: Duration = deadline.saturating_duration_since(Instant::now());
+            match lines.recv_timeout(timeout) {
+                Ok(line) => {
+                    let done: bool = line.trim() == expected;
+                    received.push(line);
+                    if done {
+                        return Ok(received);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("engine didn't answer with {expected}"),
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::BrokenPipe,
+                        "engine has exited",
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{os::unix::fs::PermissionsExt, path::PathBuf};
+
+    /// Writes a shell script standing in for an engine, it answers the handshake
+    /// and plays `best_moves` in turn to each `go`, the last one to any further `go`
+    fn scripted_engine(name: &str, best_moves: &[&str]) -> PathBuf {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("chess-rs-{name}-{}.sh", std::process::id()));
+        let script: String = format!(
+            r#"#!/bin/sh
+set -- {}
+while read -r line; do
+    case "$line" in
+        uci) echo "id name Scripted {name}"; echo "uciok" ;;
+        isready) echo "readyok" ;;
+        go*) best_move=$1
+             if [ $# -gt 1 ]; then shift; fi
+             echo "info depth 7 score cp -35 nodes 1200 nps 60000 pv $best_move"
+             echo "bestmove $best_move" ;;
+        quit) exit 0 ;;
+    esac
+done
+"#,
+            best_moves.join(" ")
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    /// Polls until the engine sends a best move
+    fn wait_for_best_move(engine: &mut ExternalEngine) -> (Vec<EngineInfo>, Option<Move>) {
+        let mut infos: Vec<EngineInfo> = Vec::new();
+        let deadline: Instant = Instant::now() + HANDSHAKE_TIMEOUT;
+        while Instant::now() < deadline {
+            match engine.poll().unwrap() {
+                Some(EngineEvent::Info(info)) => infos.push(info),
+                Some(EngineEvent::BestMove(best_move)) => return (infos, best_move),
+                None => std::thread::sleep(Duration::from_millis(5)),
+            }
+        }
+        panic!("engine didn't send a best move");
+    }
+
+    #[test]
+    fn plays_moves_of_the_engine() {
+        let path: PathBuf = scripted_engine("e5", &["e7e5"]);
+        let mut engine: ExternalEngine = ExternalEngine::spawn(&path).unwrap();
+        assert_eq!(engine.name(), Some("Scripted e5"));
+
+        let start: Position = Position::new();
+        let e4: Move = uci_to_move(&start, "e2e4").unwrap();
+        engine
+            .go(&start, &[e4], Duration::from_millis(100))
+            .unwrap();
+        assert!(engine.is_searching());
+
+        let (infos, best_move) = wait_for_best_move(&mut engine);
+        assert_eq!(
+            infos,
+            [EngineInfo {
+                depth: Some(7),
+                score: Some(EngineScore::Centipawns(-35)),
+                nodes: Some(1200),
+                nps: Some(60000),
+                pv: vec!["e7e5".to_string()],
+            }]
+        );
+        assert_eq!(best_move.map(|mv| mv.to_uci()), Some("e7e5".to_string()));
+        assert!(!engine.is_searching());
+
+        drop(engine);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn drops_best_move_of_stopped_search() {
+        let path: PathBuf = scripted_engine("stopped", &["e2e4", "e7e5"]);
+        let mut engine: ExternalEngine = ExternalEngine::spawn(&path).unwrap();
+        let start: Position = Position::new();
+        engine.go(&start, &[], Duration::from_millis(100)).unwrap();
+        engine.stop().unwrap();
+        assert!(!engine.is_searching());
+
+        // Best move of the stopped search arrives while the next one runs
+        let e4: Move = uci_to_move(&start, "e2e4").unwrap();
+        engine
+            .go(&start, &[e4], Duration::from_millis(100))
+            .unwrap();
+        assert_eq!(
+            wait_for_best_move(&mut engine).1.map(|mv| mv.to_uci()),
+            Some("e7e5".to_string())
+        );
+
+        drop(engine);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_illegal_best_move() {
+        let path: PathBuf = scripted_engine("illegal", &["e2e5"]);
+        let mut engine: ExternalEngine = ExternalEngine::spawn(&path).unwrap();
+        engine
+            .go(&Position::new(), &[], Duration::from_millis(100))
+            .unwrap();
+        assert_eq!(wait_for_best_move(&mut engine).1, None);
+
+        drop(engine);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn fails_to_spawn_missing_engine() {
+        assert!(ExternalEngine::spawn(Path::new("/nonexistent/engine")).is_err());
+    }
+
+    #[test]
+    fn parses_info_lines() {
+        assert_eq!(
+            EngineInfo::parse("info depth 3 seldepth 5 score mate -2 nodes 42 pv e2e4 e7e5"),
+            Some(EngineInfo {
+                depth: Some(3),
+                score: Some(EngineScore::Mate(-2)),
+                nodes: Some(42),
+                nps: None,
+                pv: vec!["e2e4".to_string(), "e7e5".to_string()],
+            })
+        );
+        assert_eq!(EngineInfo::parse("info string hello"), None);
+        assert_eq!(EngineInfo::parse("bestmove e2e4"), None);
+    }
+}
@@ -0,0 +1,497 @@
+//! Universal Chess Interface for the engine of chess-core, so it can play in any chess GUI<br />
+//! https://www.wbec-ridderkerk.nl/html/UCIProtocol.html<br />
+//! Also talks to other UCI engines, so the game can play against them
+
+mod external;
+
+pub use crate::external::*;
+use chess_core::*;
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+const ENGINE_NAME: &str = "chess-rs";
+const ENGINE_AUTHOR: &str = "Adamekka";
+
+/// Deepest search of `go infinite` and of `go` without limits
+const MAX_DEPTH: u8 = 64;
+const MAX_HASH_SIZE_MB: usize = 4096;
+const MAX_THREADS: usize = 256;
+
+/// Time kept in reserve so the engine doesn't lose on time while answering
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// Search running on its own thread, so `stop` can be read meanwhile
+struct RunningSearch {
+    thread: JoinHandle<()>,
+    stop: Arc<AtomicBool>,
+    /// `go infinite` only ends with `stop`
+    infinite: bool,
+}
+
+impl RunningSearch {
+    /// Stops the search and waits for its `bestmove`
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Finished infinite search is parked until told to stop
+        self.thread.thread().unpark();
+        let _ = self.thread.join();
+    }
+}
+
+/// Engine state between UCI commands, answers are written to `output`
+pub struct UciEngine<W: Write + Send + 'static> {
+    output: Arc<Mutex<W>>,
+    position: Position,
+    /// Hashes of the positions from `position` command, the current one last
+    history: Vec<u64>,
+    /// Shared by the threads of the search
+    tt: Arc<TranspositionTable>,
+    /// Syzygy tables of `setoption name SyzygyPath`, shared with the search thread
+    tablebase: Option<Arc<Tablebase>>,
+    threads: usize,
+    /// Search enhancements turned on and off with their `setoption`, for benchmarking
+    features: SearchFeatures,
+    search: Option<RunningSearch>,
+}
+
+impl<W: Write + Send + 'static> UciEngine<W> {
+    pub fn new(output: Arc<Mutex<W>>) -> Self {
+        let position: Position = Position::new();
+        Self {
+            output,
+            history: vec![position.hash()],
+            position,
+            tt: Arc::new(TranspositionTable::default()),
+            tablebase: None,
+            threads: 1,
+            features: SearchFeatures::default(),
+            search: None,
+        }
+    }
+
+    /// Handles one line of input, returns false on `quit`<br />
+    /// Unknown commands are ignored like the protocol asks
+    pub fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        match *command {
+            "uci" => {
+                self.send(&format!("id name {ENGINE_NAME}"));
+                self.send(&format!("id author {ENGINE_AUTHOR}"));
+                self.send(&format!(
+                    "option name Hash type spin default {DEFAULT_HASH_SIZE_MB} min 1 max {MAX_HASH_SIZE_MB}"
+                ));
+                self.send(&format!(
+                    "option name Threads type spin default 1 min 1 max {MAX_THREADS}"
+                ));
+                self.send("option name SyzygyPath type string default <empty>");
+                for (name, enabled) in self.features.options() {
+                    self.send(&format!("option name {name} type check default {enabled}"));
+                }
+                self.send("uciok");
+            }
+            "isready" => self.send("readyok"),
+            "setoption" => self.set_option(args),
+            "ucinewgame" => {
+                self.stop();
+                self.tt.clear();
+            }
+            "position" => {
+                self.stop();
+                self.set_position(args);
+            }
+            "go" => {
+                self.stop();
+                self.go(args);
+            }
+            "stop" => self.stop(),
+            "quit" => {
+                self.stop();
+                return false;
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    /// Stops the running search and waits for its `bestmove`
+    pub fn stop(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop();
+        }
+    }
+
+    /// Waits for the running search to finish, an infinite one is stopped instead
+    pub fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            if search.infinite {
+                search.stop();
+            } else {
+                let _ = search.thread.join();
+            }
+        }
+    }
+
+    /// Number of threads set with `setoption name Threads`
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    fn send(&self, line: &str) {
+        send(&self.output, line);
+    }
+
+    /// `setoption name <name> value <value>`, names are case insensitive
+    fn set_option(&mut self, args: &[&str]) {
+        let Some(value_index) = args.iter().position(|arg| *arg == "value") else {
+            return;
+        };
+        let name: String = args[1.min(value_index)..value_index].join(" ");
+        let value: String = args[value_index + 1..].join(" ");
+
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(size) if (1..=MAX_HASH_SIZE_MB).contains(&size) => {
+                    self.stop();
+                    self.tt = Arc::new(TranspositionTable::new(size));
+                }
+                _ => self.send(&format!("info string Invalid Hash value {value}")),
+            },
+            "threads" => match value.parse::<usize>() {
+                Ok(threads) if (1..=MAX_THREADS).contains(&threads) => self.threads = threads,
+                _ => self.send(&format!("info string Invalid Threads value {value}")),
+            },
+            "syzygypath" if value.is_empty() || value == "<empty>" => {
+                self.stop();
+                self.tablebase = None;
+            }
+            "syzygypath" => {
+                self.stop();
+                self.tablebase = match Tablebase::open(&value) {
+                    Ok(tablebase) => {
+                        self.send(&format!(
+                            "info string Found {} tablebases with up to {} pieces",
+                            tablebase.len(),
+                            tablebase.max_pieces()
+                        ));
+                        Some(Arc::new(tablebase))
+                    }
+                    Err(error) => {
+                        self.send(&format!(
+                            "info string Couldn't read SyzygyPath {value}: {error}"
+                        ));
+                        None
+                    }
+                };
+            }
+            _ => match (self.features.option_mut(&name), value.parse::<bool>()) {
+                (Some(enabled), Ok(value)) => *enabled = value,
+                (Some(_), Err(_)) => {
+                    self.send(&format!("info string Invalid {name} value {value}"))
+                }
+                (None, _) => self.send(&format!("info string Unknown option {name}")),
+            },
+        }
+    }
+
+    /// `position startpos [moves ...]` or `position fen <fen> [moves ...]`
+    fn set_position(&mut self, args: &[&str]) {
+        let moves_index: usize = args
+            .iter()
+            .position(|arg| *arg == "moves")
+            .unwrap_or(args.len());
+
+        let position: Position = match args.first() {
+            Some(&"startpos") => Position::new(),
+            Some(&"fen") => match Position::from_fen(&args[1..moves_index].join(" ")) {
+                Ok(position) => position,
+                Err(error) => {
+                    self.send(&format!("info string Invalid FEN: {error}"));
+                    return;
+                }
+            },
+            _ => return,
+        };
+
+        self.history = vec![position.hash()];
+        self.position = position;
+        for uci in args.iter().skip(moves_index + 1) {
+            let Some(mv) = uci_to_move(&self.position, uci) else {
+                self.send(&format!("info string Illegal move {uci}"));
+                return;
+            };
+            self.position = self.position.make_move(mv);
+            self.history.push(self.position.hash());
+        }
+    }
+
+    /// `go [depth <plies>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>]
+    /// [movestogo <n>] [infinite]`
+    fn go(&mut self, args: &[&str]) {
+        let value = |name: &str| -> Option<u64> {
+            args.iter()
+                .position(|arg| *arg == name)
+                .and_then(|index| args.get(index + 1))
+                .and_then(|value| value.parse().ok())
+        };
+
+        let (time, increment): (Option<u64>, Option<u64>) = match self.position.color_to_move {
+            PieceColor::White => (value("wtime"), value("winc")),
+            PieceColor::Black => (value("btime"), value("binc")),
+        };
+
+        // Spend a share of the remaining time, never more than what's left
+        let infinite: bool = args.contains(&"infinite");
+        let time: Option<Duration> = if infinite {
+            None
+        } else if let Some(movetime) = value("movetime") {
+            Some(Duration::from_millis(movetime))
+        } else {
+            time.map(|time| {
+                let moves_to_go: u64 = value("movestogo").unwrap_or(30).max(1);
+                let time: Duration = Duration::from_millis(time);
+                let share: Duration =
+                    time / moves_to_go as u32 + Duration::from_millis(increment.unwrap_or(0)) / 2;
+                share.min(time.saturating_sub(MOVE_OVERHEAD))
+            })
+        };
+
+        let limits: SearchLimits = SearchLimits {
+            depth: value("depth").map_or(MAX_DEPTH, |depth| depth.clamp(1, MAX_DEPTH as u64) as u8),
+            time,
+            threads: self.threads,
+            features: self.features,
+            ..SearchLimits::default()
+        };
+
+        let stop: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let thread: JoinHandle<()> = {
+            let output: Arc<Mutex<W>> = self.output.clone();
+            let tt: Arc<TranspositionTable> = self.tt.clone();
+            let tablebase: Option<Arc<Tablebase>> = self.tablebase.clone();
+            let position: Position = self.position.clone();
+            let history: Vec<u64> = self.history.clone();
+            let stop: Arc<AtomicBool> = stop.clone();
+
+            std::thread::spawn(move || {
+                let result: SearchResult = search_with_stop(
+                    &position,
+                    &history,
+                    limits,
+                    &tt,
+                    tablebase.as_deref(),
+                    &stop,
+                    &mut |iteration| send(&output, &info_line(iteration)),
+                );
+
+                // Infinite search that ended early, e.g. on a mate, waits for `stop` to answer
+                while infinite && !stop.load(Ordering::Relaxed) {
+                    std::thread::park();
+                }
+                send(
+                    &output,
+                    &format!(
+                        "bestmove {}",
+                        result
+                            .best_move
+                            .map_or("0000".to_string(), |mv| mv.to_uci())
+                    ),
+                );
+            })
+        };
+
+        self.search = Some(RunningSearch {
+            thread,
+            stop,
+            infinite,
+        });
+    }
+}
+
+/// Returns the `info` line of a finished iteration
+fn info_line(result: &SearchResult) -> String {
+    let score: String = match result.mate_in() {
+        Some(moves) => format!("mate {moves}"),
+        None => format!("cp {}", result.score),
+    };
+    format!(
+        "info depth {} score {score} nodes {} time {} nps {}",
+        result.depth,
+        result.nodes,
+        result.time.as_millis(),
+        result.nodes_per_second()
+    )
+}
+
+fn send<W: Write>(output: &Mutex<W>, line: &str) {
+    let mut output = output
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _ = writeln!(output, "{line}");
+    let _ = output.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the commands, waits for the search and returns everything the engine wrote
+    fn run(commands: &[&str]) -> Vec<String> {
+        let output: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+        let mut engine: UciEngine<Vec<u8>> = UciEngine::new(output.clone());
+        for command in commands {
+            engine.handle(command);
+        }
+        engine.wait();
+
+        let output: Vec<u8> = output.lock().unwrap().clone();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn identifies_itself() {
+        let output: Vec<String> = run(&["uci", "isready"]);
+        assert!(output[0].starts_with("id name"));
+        assert!(output
+            .iter()
+            .any(|line| line.starts_with("option name Hash")));
+        assert!(output
+            .iter()
+            .any(|line| line.starts_with("option name Threads")));
+        assert_eq!(output[output.len() - 2..], ["uciok", "readyok"]);
+    }
+
+    #[test]
+    fn plays_from_position_with_moves() {
+        // Scholar's mate is on the board after these moves
+        let output: Vec<String> = run(&[
+            "position startpos moves e2e4 e7e5 f1c4 b8c6 d1h5 g8f6",
+            "go depth 2",
+        ]);
+        assert_eq!(output.last().unwrap(), "bestmove h5f7");
+        assert!(output[output.len() - 2].contains("score mate 1"));
+
+        let output: Vec<String> = run(&[
+            "position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            "go movetime 500",
+        ]);
+        assert_eq!(output.last().unwrap(), "bestmove a1a8");
+    }
+
+    #[test]
+    fn reports_bad_input() {
+        let output: Vec<String> = run(&[
+            "position fen 8/8/8 w - - 0 1",
+            "position startpos moves e2e5",
+            "setoption name Hash value 0",
+            "setoption name Threads value 4",
+            "setoption name Ponder value true",
+            "foo",
+        ]);
+        assert_eq!(
+            output,
+            [
+                "info string Invalid FEN: expected 8 ranks, found 3",
+                "info string Illegal move e2e5",
+                "info string Invalid Hash value 0",
+                "info string Unknown option Ponder",
+            ]
+        );
+    }
+
+    #[test]
+    fn toggles_search_features() {
+        let output: Vec<String> = run(&["uci"]);
+        assert!(output
+            .iter()
+            .any(|line| line == "option name NullMovePruning type check default true"));
+
+        let output: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+        let mut engine: UciEngine<Vec<u8>> = UciEngine::new(output.clone());
+        engine.handle("setoption name nullmovepruning value false");
+        engine.handle("setoption name LateMoveReductions value maybe");
+        assert!(!engine.features.null_move_pruning);
+        assert!(engine.features.late_move_reductions);
+        assert_eq!(
+            String::from_utf8(output.lock().unwrap().clone()).unwrap(),
+            "info string Invalid LateMoveReductions value maybe\n"
+        );
+    }
+
+    #[test]
+    fn sets_syzygy_path() {
+        let output: Vec<String> = run(&[
+            "setoption name SyzygyPath value <empty>",
+            "setoption name SyzygyPath value /nonexistent/syzygy",
+        ]);
+        assert_eq!(output.len(), 1);
+        assert!(output[0].starts_with("info string Couldn't read SyzygyPath /nonexistent/syzygy"));
+    }
+
+    #[test]
+    fn stops_infinite_search() {
+        let output: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+        let mut engine: UciEngine<Vec<u8>> = UciEngine::new(output.clone());
+        engine.handle("setoption name Threads value 2");
+        assert_eq!(engine.threads(), 2);
+        engine.handle("position startpos");
+        engine.handle("go infinite");
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(engine.handle("stop"));
+        assert!(!engine.handle("quit"));
+
+        let output: String = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(output.lines().last().unwrap().starts_with("bestmove "));
+        // Each finished iteration reports the nodes of both threads and their speed
+        let infos: Vec<&str> = output
+            .lines()
+            .filter(|line| line.starts_with("info depth "))
+            .collect();
+        assert!(!infos.is_empty());
+        assert!(infos.iter().all(|line| line.contains(" nps ")));
+    }
+
+    #[test]
+    fn holds_best_move_of_infinite_search_until_stop() {
+        let output: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+        let mut engine: UciEngine<Vec<u8>> = UciEngine::new(output.clone());
+        // Mate in 1, found long before the search is stopped
+        engine.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        engine.handle("go infinite");
+        std::thread::sleep(Duration::from_millis(300));
+
+        let lines = || -> Vec<String> {
+            let output: String = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+            output.lines().map(str::to_string).collect()
+        };
+        let thinking: Vec<String> = lines();
+        assert!(!thinking.is_empty());
+        assert!(thinking.iter().all(|line| line.starts_with("info depth ")));
+
+        assert!(engine.handle("stop"));
+        let stopped: Vec<String> = lines();
+        assert!(stopped[thinking.len() - 1].contains("score mate 1"));
+        assert_eq!(stopped.last().unwrap(), "bestmove a1a8");
+    }
+
+    #[test]
+    fn uses_clock_time() {
+        let output: Vec<String> = run(&["position startpos", "go wtime 1000 btime 1000"]);
+        assert!(output.last().unwrap().starts_with("bestmove "));
+    }
+}
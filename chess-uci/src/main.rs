@@ -0,0 +1,22 @@
+use chess_uci::UciEngine;
+use std::{
+    io::{BufRead, Stdout},
+    sync::{Arc, Mutex},
+};
+
+/// Runs the engine on stdin and stdout until `quit` or the end of input<br />
+/// Search started by the last command still finishes when the input ends
+fn main() {
+    let mut engine: UciEngine<Stdout> = UciEngine::new(Arc::new(Mutex::new(std::io::stdout())));
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !engine.handle(&line) {
+            break;
+        }
+    }
+
+    engine.wait();
+}
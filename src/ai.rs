@@ -0,0 +1,825 @@
+use crate::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use chess_uci::{EngineEvent, EngineInfo, EngineScore, ExternalEngine};
+use futures_lite::future;
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub struct AIPlugin;
+impl Plugin for AIPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AIColor>()
+            .init_resource::<AISearch>()
+            .init_resource::<AIRandom>()
+            .add_startup_system(init_ai_ui)
+            .add_startup_system(init_transposition_table)
+            .add_startup_system(init_external_engine)
+            .add_startup_system(init_opening_book)
+            .add_system(start_ai_search)
+            .add_system(poll_ai_search.after(start_ai_search).before(play_move))
+            .add_system(cancel_ai_search_on_exit)
+            .add_system(update_thinking_ui.after(poll_ai_search))
+            .add_system(difficulty_button_ui)
+            .add_system(threads_button_ui)
+            .add_system(update_book_ui);
+    }
+}
+
+/// Color the AI plays when it's enabled, the human plays the other one<br />
+/// Set to the opponent of the player to move when the AI gets enabled
+#[derive(Debug, Resource)]
+pub struct AIColor(pub PieceColor);
+
+impl Default for AIColor {
+    fn default() -> Self {
+        Self(PieceColor::Black)
+    }
+}
+
+/// How strong the AI plays, chosen in the UI and kept in the settings
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Difficulty {
+    Beginner,
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    Master,
+}
+
+impl Difficulty {
+    const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Master,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "beginner",
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Master => "master",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name() == name)
+    }
+
+    /// Returns the next harder difficulty, the easiest one after the hardest
+    fn next(&self) -> Difficulty {
+        let index: usize = Difficulty::ALL
+            .iter()
+            .position(|difficulty| difficulty == self)
+            .unwrap_or(0);
+        Difficulty::ALL[(index + 1) % Difficulty::ALL.len()]
+    }
+
+    /// Returns the search depth, time and noise of the difficulty
+    fn search_limits(&self, seed: u64) -> SearchLimits {
+        let (depth, time, noise): (u8, u64, i32) = match self {
+            Difficulty::Beginner => (1, 200, 300),
+            Difficulty::Easy => (2, 500, 120),
+            Difficulty::Medium => (4, 1_000, 30),
+            Difficulty::Hard => (6, 3_000, 0),
+            Difficulty::Master => (20, 8_000, 0),
+        };
+
+        SearchLimits {
+            depth,
+            time: Some(Duration::from_millis(time)),
+            noise,
+            seed,
+            ..SearchLimits::default()
+        }
+    }
+
+    /// Returns the probability of playing a random legal move instead of searching
+    fn blunder_probability(&self) -> f64 {
+        match self {
+            Difficulty::Beginner => 0.2,
+            Difficulty::Easy => 0.08,
+            Difficulty::Medium => 0.02,
+            Difficulty::Hard | Difficulty::Master => 0.0,
+        }
+    }
+}
+
+/// Random numbers of the AI's noise, blunders and book moves<br />
+/// SplitMix64, https://prng.di.unimi.it/splitmix64.c, seeded from the clock unless given a seed
+#[derive(Debug, Resource)]
+pub struct AIRandom(u64);
+
+impl AIRandom {
+    /// Same seed gives the same numbers, so the AI plays the same moves
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z: u64 = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number from 0 up to but excluding 1
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Default for AIRandom {
+    fn default() -> Self {
+        let nanos: u128 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos());
+        Self::new(nanos as u64)
+    }
+}
+
+#[derive(Component)]
+struct DifficultyButton;
+
+#[derive(Component)]
+struct ThreadsButton;
+
+const DIFFICULTY_BUTTON: BackgroundColor = BackgroundColor(Color::rgb(0.15, 0.15, 0.15));
+const DIFFICULTY_BUTTON_HOVER: BackgroundColor = BackgroundColor(Color::rgb(0.25, 0.25, 0.25));
+
+/// Transposition table kept between the AI's searches, shared by the threads of the running search
+#[derive(Resource)]
+pub struct AITranspositionTable(Arc<TranspositionTable>);
+
+fn init_transposition_table(mut commands: Commands, settings: Res<Settings>) {
+    info!(
+        "Transposition table size: {} MB, {} search threads",
+        settings.hash_size_mb, settings.threads
+    );
+    commands.insert_resource(AITranspositionTable(Arc::new(TranspositionTable::new(
+        settings.hash_size_mb,
+    ))));
+}
+
+/// External UCI engine playing instead of the built-in search, if one is configured
+#[derive(Default, Resource)]
+pub struct ExternalEngineOpponent(Option<ExternalEngine>);
+
+/// Starts the engine configured in the settings, the built-in search plays if it can't start
+fn init_external_engine(mut commands: Commands, settings: Res<Settings>) {
+    let Some(path) = &settings.engine_path else {
+        commands.init_resource::<ExternalEngineOpponent>();
+        return;
+    };
+
+    let engine: Option<ExternalEngine> = match ExternalEngine::spawn(path) {
+        Ok(mut engine) => {
+            info!(
+                "Playing against {} from {}",
+                engine.name().unwrap_or("engine"),
+                path.display()
+            );
+            if let Err(error) = engine.set_option("Hash", &settings.hash_size_mb.to_string()) {
+                warn!("Couldn't set engine's hash size: {}", error);
+            }
+            if let Err(error) = engine.set_option("Threads", &settings.threads.to_string()) {
+                warn!("Couldn't set engine's thread count: {}", error);
+            }
+            Some(engine)
+        }
+        Err(error) => {
+            warn!("Couldn't start engine {}: {}", path.display(), error);
+            None
+        }
+    };
+    commands.insert_resource(ExternalEngineOpponent(engine));
+}
+
+/// Polyglot opening book the AI plays from while the game is in it, if one is configured
+#[derive(Default, Resource)]
+pub struct AIOpeningBook(Option<OpeningBook>);
+
+/// Reads the book configured in the settings, the AI searches from the first move if it can't
+fn init_opening_book(mut commands: Commands, settings: Res<Settings>) {
+    let Some(path) = &settings.book_path else {
+        commands.init_resource::<AIOpeningBook>();
+        return;
+    };
+
+    let book: Option<OpeningBook> = match OpeningBook::open(path) {
+        Ok(book) => {
+            info!(
+                "Opening book {} with {} entries",
+                path.display(),
+                book.len()
+            );
+            Some(book)
+        }
+        Err(error) => {
+            warn!("Couldn't read opening book {}: {}", path.display(), error);
+            None
+        }
+    };
+    commands.insert_resource(AIOpeningBook(book));
+}
+
+/// Where the AI's move comes from
+enum SearchJob {
+    /// Move picked from the opening book, played on the next poll
+    Book(Move),
+    /// Built-in search running on the async compute task pool
+    BuiltIn(Task<SearchResult>),
+    /// External engine searching in its own process
+    External,
+}
+
+/// Search of the AI's move<br />
+/// Dropping it cancels a built-in search, so a replaced or removed search doesn't keep a thread busy
+struct RunningSearch {
+    job: SearchJob,
+    stop: Arc<AtomicBool>,
+    /// Position the search started from, the result is thrown away if the game moved on
+    position: Position,
+    /// Latest progress reported by an external engine
+    info: Option<EngineInfo>,
+}
+
+impl Drop for RunningSearch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// AI search in progress, if any
+#[derive(Default, Resource)]
+pub struct AISearch(Option<RunningSearch>);
+
+impl AISearch {
+    pub fn is_thinking(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Stops the running search, its move is never played
+    pub fn cancel(&mut self, engine: &mut ExternalEngineOpponent) {
+        let Some(running_search) = self.0.take() else {
+            return;
+        };
+
+        info!("AI search cancelled");
+        if let (SearchJob::External, Some(engine)) = (&running_search.job, &mut engine.0) {
+            if let Err(error) = engine.stop() {
+                warn!("Couldn't stop engine: {}", error);
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+struct ThinkingText;
+
+#[derive(Component)]
+struct BookText;
+
+/// Starts searching the best move in the background when it's the AI's turn
+#[allow(clippy::too_many_arguments)]
+fn start_ai_search(
+    ai_enabled_query: Query<&AIEnabled>,
+    ai_color: Res<AIColor>,
+    mut ai_search: ResMut<AISearch>,
+    game: Res<Game>,
+    game_over: Res<GameOver>,
+    pending_promotion: Res<PendingPromotion>,
+    replay: Res<Replay>,
+    settings: Res<Settings>,
+    transposition_table: Res<AITranspositionTable>,
+    mut engine: ResMut<ExternalEngineOpponent>,
+    book: Res<AIOpeningBook>,
+    tablebase: Res<SyzygyTablebase>,
+    mut random: ResMut<AIRandom>,
+    turn: Res<Turn>,
+) {
+    if !turn.is_changed() && !ai_color.is_changed() {
+        return;
+    }
+
+    let Ok(ai_enabled) = ai_enabled_query.get_single() else {
+        return;
+    };
+    if !ai_enabled.0
+        || game.position.color_to_move != ai_color.0
+        || game_over.result.is_some()
+        || pending_promotion.entity.is_some()
+        || replay.is_active()
+        || ai_search.is_thinking()
+    {
+        return;
+    }
+
+    let difficulty: Difficulty = settings.difficulty;
+    let position: Position = game.position.clone();
+    let stop: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+
+    // Book moves come before any search, so games don't all open the same way
+    if let Some(mv) = book
+        .0
+        .as_ref()
+        .and_then(|book| book.choose_move(&position, random.next_u64()))
+    {
+        ai_search.0 = Some(RunningSearch {
+            job: SearchJob::Book(mv),
+            stop,
+            position,
+            info: None,
+        });
+        return;
+    }
+
+    // External engine gets the difficulty's thinking time, noise and blunders are built-in only
+    if let Some(external_engine) = &mut engine.0 {
+        let movetime: Duration = difficulty.search_limits(0).time.unwrap_or_default();
+        match external_engine.go(&game.start_position, &game.moves, movetime) {
+            Ok(()) => {
+                info!("Engine is thinking for {:?}", movetime);
+                ai_search.0 = Some(RunningSearch {
+                    job: SearchJob::External,
+                    stop,
+                    position,
+                    info: None,
+                });
+            }
+            Err(error) => {
+                warn!(
+                    "Engine stopped working, the built-in AI takes over: {}",
+                    error
+                );
+                engine.0 = None;
+            }
+        }
+        return;
+    }
+
+    let task: Task<SearchResult> = {
+        let position: Position = position.clone();
+        let history: Vec<u64> = game.position_history.clone();
+        let tt: Arc<TranspositionTable> = transposition_table.0.clone();
+        let threads: usize = settings.threads;
+        let stop: Arc<AtomicBool> = stop.clone();
+        let tablebase: Option<Arc<Tablebase>> = tablebase.0.clone();
+        let blunders: bool = random.next_f64() < difficulty.blunder_probability();
+        let blunder_index: usize = random.next_u64() as usize;
+        let seed: u64 = random.next_u64();
+        AsyncComputeTaskPool::get().spawn(async move {
+            // Weaker difficulties sometimes play a random move instead of the searched one
+            let moves: Vec<Move> = generate_legal_moves(&position);
+            if !moves.is_empty() && blunders {
+                return SearchResult {
+                    best_move: Some(moves[blunder_index % moves.len()]),
+                    score: 0,
+                    depth: 0,
+                    nodes: 0,
+                    time: Duration::ZERO,
+                };
+            }
+
+            let limits: SearchLimits = SearchLimits {
+                threads,
+                ..difficulty.search_limits(seed)
+            };
+            search_with_stop(
+                &position,
+                &history,
+                limits,
+                &tt,
+                tablebase.as_deref(),
+                &stop,
+                &mut |_| {},
+            )
+        })
+    };
+
+    info!("AI is thinking at {} difficulty", difficulty.name());
+    ai_search.0 = Some(RunningSearch {
+        job: SearchJob::BuiltIn(task),
+        stop,
+        position,
+        info: None,
+    });
+}
+
+/// Plays the AI's move like a clicked move once the search is done<br />
+/// Cancels the search if the AI got disabled or the game no longer is in the searched position
+fn poll_ai_search(
+    ai_enabled_query: Query<&AIEnabled>,
+    mut ai_search: ResMut<AISearch>,
+    mut engine: ResMut<ExternalEngineOpponent>,
+    game: Res<Game>,
+    game_over: Res<GameOver>,
+    mut move_events: EventWriter<MoveEvent>,
+) {
+    let Some(running_search) = ai_search.0.as_mut() else {
+        return;
+    };
+
+    let ai_enabled: bool = ai_enabled_query
+        .get_single()
+        .is_ok_and(|ai_enabled| ai_enabled.0);
+    if !ai_enabled || game_over.result.is_some() || running_search.position != game.position {
+        ai_search.cancel(&mut engine);
+        return;
+    }
+
+    let best_move: Option<Move> = match &mut running_search.job {
+        SearchJob::Book(mv) => {
+            info!(
+                "AI plays {} from the book",
+                move_to_san(&game.position, *mv)
+            );
+            Some(*mv)
+        }
+        SearchJob::BuiltIn(task) => {
+            let Some(result) = future::block_on(future::poll_once(task)) else {
+                return;
+            };
+            if let Some(mv) = result.best_move {
+                info!(
+                    "AI plays {} at depth {}, score {}, {} nodes, {} nodes/s",
+                    move_to_san(&game.position, mv),
+                    result.depth,
+                    result.score,
+                    result.nodes,
+                    result.nodes_per_second()
+                );
+            }
+            result.best_move
+        }
+        SearchJob::External => {
+            let Some(external_engine) = &mut engine.0 else {
+                ai_search.0 = None;
+                return;
+            };
+
+            // Read everything the engine sent this frame, the latest info is shown
+            loop {
+                match external_engine.poll() {
+                    Ok(Some(EngineEvent::Info(info))) => running_search.info = Some(info),
+                    Ok(Some(EngineEvent::BestMove(best_move))) => {
+                        match best_move {
+                            Some(mv) => info!("Engine plays {}", move_to_san(&game.position, mv)),
+                            None => warn!("Engine didn't send a legal move"),
+                        }
+                        break best_move;
+                    }
+                    Ok(None) => return,
+                    Err(error) => {
+                        warn!(
+                            "Engine stopped working, the built-in AI takes over: {}",
+                            error
+                        );
+                        engine.0 = None;
+                        break None;
+                    }
+                }
+            }
+        }
+    };
+    ai_search.0 = None;
+
+    if let Some(mv) = best_move {
+        move_events.send(MoveEvent { mv });
+    }
+}
+
+/// Stops the search when the window closes, so the app doesn't wait for it
+fn cancel_ai_search_on_exit(
+    mut exit_events: EventReader<bevy::app::AppExit>,
+    mut ai_search: ResMut<AISearch>,
+    mut engine: ResMut<ExternalEngineOpponent>,
+) {
+    if exit_events.iter().count() > 0 {
+        ai_search.cancel(&mut engine);
+    }
+}
+
+fn init_ai_ui(mut commands: Commands, asset_server: ResMut<AssetServer>, settings: Res<Settings>) {
+    let font: Handle<Font> = asset_server.load("fonts/UbuntuMonoNerdFontCompleteMono.ttf");
+
+    // Difficulty and threads buttons below the AI button
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                align_self: AlignSelf::FlexEnd,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(80.0),
+                    left: Val::Px(5.0),
+                    ..default()
+                },
+                gap: Size::width(Val::Px(5.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(ButtonBundle {
+                    background_color: DIFFICULTY_BUTTON,
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        format!("Difficulty: {}", settings.difficulty.name()),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                })
+                .insert(DifficultyButton);
+
+            parent
+                .spawn(ButtonBundle {
+                    background_color: DIFFICULTY_BUTTON,
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        format!("Threads: {}", settings.threads),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                })
+                .insert(ThreadsButton);
+        });
+
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(65.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ThinkingText);
+
+    // Book indicator above the thinking text
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font,
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(95.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(BookText);
+}
+
+/// Shows "Thinking..." while the AI searches
+fn update_thinking_ui(ai_search: Res<AISearch>, mut query: Query<&mut Text, With<ThinkingText>>) {
+    if !ai_search.is_changed() {
+        return;
+    }
+
+    // External engine tells how far it got
+    let info: String = match ai_search.0.as_ref().and_then(|search| search.info.as_ref()) {
+        Some(info) => {
+            let depth: String = info
+                .depth
+                .map_or(String::new(), |depth| format!(" depth {depth}"));
+            let score: String = match info.score {
+                Some(EngineScore::Centipawns(score)) => {
+                    format!(" {:+.2}", score as f32 / PAWN_VALUE as f32)
+                }
+                Some(EngineScore::Mate(moves)) => format!(" mate {moves}"),
+                None => String::new(),
+            };
+            format!("{depth}{score}")
+        }
+        None => String::new(),
+    };
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = if ai_search.is_thinking() {
+            format!("Thinking...{info}")
+        } else {
+            String::new()
+        };
+    }
+}
+
+/// Switches to the next difficulty when the button is clicked and saves it for the next session
+#[allow(clippy::type_complexity)]
+fn difficulty_button_ui(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &Children),
+        (Changed<Interaction>, With<DifficultyButton>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut settings: ResMut<Settings>,
+) {
+    for (interaction, mut color, children) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                settings.difficulty = settings.difficulty.next();
+                info!("Difficulty: {}", settings.difficulty.name());
+
+                if let Ok(mut text) = text_query.get_mut(children[0]) {
+                    text.sections[0].value = format!("Difficulty: {}", settings.difficulty.name());
+                }
+                if let Err(error) = settings.save() {
+                    warn!("Couldn't save settings: {}", error);
+                }
+            }
+            Interaction::Hovered => color.0 = DIFFICULTY_BUTTON_HOVER.0,
+            Interaction::None => color.0 = DIFFICULTY_BUTTON.0,
+        }
+    }
+}
+
+/// Returns the thread count after `threads`, back to 1 after all of the CPU's threads
+fn next_thread_count(threads: usize, available: usize) -> usize {
+    if threads >= available {
+        1
+    } else {
+        threads + 1
+    }
+}
+
+/// Switches to the next thread count when the button is clicked and saves it for the next session<br />
+/// The next search uses it
+#[allow(clippy::type_complexity)]
+fn threads_button_ui(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &Children),
+        (Changed<Interaction>, With<ThreadsButton>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut settings: ResMut<Settings>,
+) {
+    for (interaction, mut color, children) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                let available: usize =
+                    std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+                settings.threads = next_thread_count(settings.threads, available);
+                info!("Threads: {}", settings.threads);
+
+                if let Ok(mut text) = text_query.get_mut(children[0]) {
+                    text.sections[0].value = format!("Threads: {}", settings.threads);
+                }
+                if let Err(error) = settings.save() {
+                    warn!("Couldn't save settings: {}", error);
+                }
+            }
+            Interaction::Hovered => color.0 = DIFFICULTY_BUTTON_HOVER.0,
+            Interaction::None => color.0 = DIFFICULTY_BUTTON.0,
+        }
+    }
+}
+
+/// Shows "In book" while the game's position is in the opening book
+fn update_book_ui(
+    book: Res<AIOpeningBook>,
+    game: Res<Game>,
+    mut query: Query<&mut Text, With<BookText>>,
+) {
+    if !game.is_changed() && !book.is_changed() {
+        return;
+    }
+
+    let in_book: bool = book
+        .0
+        .as_ref()
+        .is_some_and(|book| book.contains(&game.position));
+    for mut text in query.iter_mut() {
+        text.sections[0].value = if in_book {
+            "In book".to_string()
+        } else {
+            String::new()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_random_numbers_of_a_seed() {
+        let numbers = |seed: u64| -> Vec<u64> {
+            let mut random: AIRandom = AIRandom::new(seed);
+            (0..8).map(|_| random.next_u64()).collect()
+        };
+        assert_eq!(numbers(7), numbers(7));
+        assert_ne!(numbers(7), numbers(8));
+
+        let mut random: AIRandom = AIRandom::new(0);
+        // First output of SplitMix64 seeded with 0
+        assert_eq!(random.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert!((0..1_000).all(|_| (0.0..1.0).contains(&random.next_f64())));
+    }
+
+    #[test]
+    fn cycles_thread_counts() {
+        assert_eq!(next_thread_count(1, 4), 2);
+        assert_eq!(next_thread_count(3, 4), 4);
+        assert_eq!(next_thread_count(4, 4), 1);
+        // More threads than the CPU has, e.g. set in the settings file
+        assert_eq!(next_thread_count(16, 4), 1);
+        assert_eq!(next_thread_count(1, 1), 1);
+    }
+
+    #[test]
+    fn master_plays_strongest() {
+        assert_eq!(Difficulty::Hard.next(), Difficulty::Master);
+        assert_eq!(Difficulty::Master.next(), Difficulty::Beginner);
+        assert_eq!(Difficulty::from_name("master"), Some(Difficulty::Master));
+
+        let master: SearchLimits = Difficulty::Master.search_limits(0);
+        let hard: SearchLimits = Difficulty::Hard.search_limits(0);
+        assert!(master.depth > hard.depth && master.time > hard.time);
+        assert_eq!(master.noise, 0);
+        assert_eq!(Difficulty::Master.blunder_probability(), 0.0);
+    }
+
+    /// Runs `poll_ai_search` once with the AI playing a book move found in the starting position,
+    /// after the game made `moves`<br />
+    /// Returns the search's stop flag and whether the move was played
+    fn poll_book_move(moves: usize) -> (bool, bool) {
+        let mut app: App = App::new();
+        app.add_event::<MoveEvent>()
+            .init_resource::<ExternalEngineOpponent>()
+            .init_resource::<GameOver>()
+            .add_system(poll_ai_search);
+        app.world.spawn(AIEnabled(true));
+
+        let position: Position = Position::new();
+        let mut game: Game = Game::new(position.clone());
+        for _ in 0..moves {
+            game.make_move(generate_legal_moves(&game.position)[0]);
+        }
+        app.insert_resource(game);
+
+        let stop: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        app.insert_resource(AISearch(Some(RunningSearch {
+            job: SearchJob::Book(generate_legal_moves(&position)[0]),
+            stop: stop.clone(),
+            position,
+            info: None,
+        })));
+
+        app.update();
+        assert!(!app.world.resource::<AISearch>().is_thinking());
+        (
+            stop.load(Ordering::Relaxed),
+            !app.world.resource::<Events<MoveEvent>>().is_empty(),
+        )
+    }
+
+    #[test]
+    fn cancels_search_of_a_left_position() {
+        assert_eq!(poll_book_move(0), (true, true));
+        // Game moved on while the AI was thinking, the search stops and its move is dropped
+        assert_eq!(poll_book_move(1), (true, false));
+    }
+}
@@ -1,8 +1,11 @@
+mod ai;
+mod replay;
+mod settings;
+mod tablebase;
 mod ui;
 
-use crate::ui::*;
+use crate::{ai::*, replay::*, settings::*, tablebase::*, ui::*};
 use bevy::{
-    app::AppExit,
     prelude::*,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
@@ -10,13 +13,21 @@ use bevy_kira_audio::{AudioControl, AudioSource};
 use bevy_mod_picking::{
     DefaultPickingPlugins, PickableBundle, PickingCameraBundle, PickingEvent, SelectionEvent,
 };
+use chess_core::*;
 use ordinal_type::Ordinal;
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 const WINDOW_TITLE: &str = "Chess by Adamekka";
 const WINDOW_WIDTH: u16 = 1280;
 const WINDOW_HEIGHT: u16 = 720;
 
 fn main() {
+    let replay: Replay = replay_from_args();
+    let game: Game = replay.start_game().unwrap_or_else(game_from_args);
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -28,19 +39,33 @@ fn main() {
         }))
         .init_resource::<SelectedSquare>()
         .init_resource::<SelectedPiece>()
-        .init_resource::<Turn>()
+        .insert_resource(Turn::new(&game.position))
+        .insert_resource(game)
+        .insert_resource(replay)
+        .insert_resource(Settings::load())
+        .init_resource::<GameOver>()
+        .add_event::<GameOverEvent>()
+        .init_resource::<PendingPromotion>()
+        .init_resource::<SanInput>()
+        .add_event::<MoveEvent>()
+        .add_event::<TurnEndEvent>()
         .add_plugins(DefaultPickingPlugins)
         .add_plugin(bevy_kira_audio::AudioPlugin)
         // .add_plugin(DebugEventsPickingPlugin)
         .add_startup_system(setup)
         .add_plugin(ui::UIPlugin)
+        .add_plugin(replay::ReplayPlugin)
+        .add_plugin(ai::AIPlugin)
+        .add_plugin(tablebase::TablebasePlugin)
         .add_system(select_piece)
         .add_system(select_square.before(select_piece))
         .add_system(get_piece_for_move.after(select_piece))
+        .add_system(type_san_move.before(play_move))
+        .add_system(play_move.after(get_piece_for_move).before(move_piece))
         .add_system(move_piece.after(select_piece))
         .add_system(despawn_captured_pieces.after(move_piece))
-        .add_system(promote_pieces.after(move_piece))
-        .add_system(get_pieces.after(move_piece))
+        .add_system(end_turn.in_base_set(CoreSet::PostUpdate))
+        .add_system(save_pgn_at_game_over)
         .run();
 }
 
@@ -54,24 +79,6 @@ struct SelectedPiece {
     entity: Option<Entity>,
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
-enum PieceType {
-    PawnBlack,
-    PawnWhite,
-    RookBlack,
-    RookWhite,
-    KnightBlack,
-    KnightWhite,
-    BishopBlack,
-    BishopWhite,
-    QueenBlack,
-    QueenWhite,
-    KingBlack,
-    KingWhite,
-    #[default]
-    None,
-}
-
 trait SortPieceType {
     fn sort(&mut self);
 }
@@ -100,289 +107,154 @@ impl SortPieceType for Vec<PieceType> {
     }
 }
 
-// https://en.wikipedia.org/wiki/Forsyth%E2%80%93Edwards_Notation
-trait ToFEN {
-    fn to_fen(&self, turn: Res<Turn>) -> String;
-}
-
-impl ToFEN for [[PieceType; 8]; 8] {
-    fn to_fen(&self, turn: Res<Turn>) -> String {
-        let mut fen: String = String::new();
-
-        for row in self.iter() {
-            let mut empty_squares: u8 = 0;
-            for piece in row.iter() {
-                match piece {
-                    PieceType::PawnWhite => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('P');
-                    }
-                    PieceType::PawnBlack => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('p');
-                    }
-                    PieceType::RookWhite => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('R');
-                    }
-                    PieceType::RookBlack => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('r');
-                    }
-                    PieceType::KnightWhite => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('N');
-                    }
-                    PieceType::KnightBlack => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('n');
-                    }
-                    PieceType::BishopWhite => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('B');
-                    }
-                    PieceType::BishopBlack => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('b');
-                    }
-                    PieceType::QueenWhite => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('Q');
-                    }
-                    PieceType::QueenBlack => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('q');
-                    }
-                    PieceType::KingWhite => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('K');
-                    }
-                    PieceType::KingBlack => {
-                        if empty_squares > 0 {
-                            fen.push_str(&empty_squares.to_string());
-                            empty_squares = 0;
-                        }
-                        fen.push('k');
-                    }
-                    PieceType::None => {
-                        empty_squares += 1;
-                    }
-                }
-            }
-            if empty_squares > 0 {
-                fen.push_str(&empty_squares.to_string());
-            }
-
-            fen.push('/');
-        }
-
-        // Remove last '/'
-        fen.pop();
-
-        let turn = match turn.color {
-            PieceColor::White => "w",
-            PieceColor::Black => "b",
-        };
-        // Casting and en passant are not implemented yet
-        // Don't calculate halfmove clock and fullmove number
-        fen.push_str(&format!(" {turn} ---- - 0 0"));
-
-        fen
+fn get_image_path(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::PawnBlack => "chess-2d-pieces/pawn_black.png",
+        PieceType::PawnWhite => "chess-2d-pieces/pawn_white.png",
+        PieceType::RookBlack => "chess-2d-pieces/rook_black.png",
+        PieceType::RookWhite => "chess-2d-pieces/rook_white.png",
+        PieceType::KnightBlack => "chess-2d-pieces/knight_black.png",
+        PieceType::KnightWhite => "chess-2d-pieces/knight_white.png",
+        PieceType::BishopBlack => "chess-2d-pieces/bishop_black.png",
+        PieceType::BishopWhite => "chess-2d-pieces/bishop_white.png",
+        PieceType::QueenBlack => "chess-2d-pieces/queen_black.png",
+        PieceType::QueenWhite => "chess-2d-pieces/queen_white.png",
+        PieceType::KingBlack => "chess-2d-pieces/king_black.png",
+        PieceType::KingWhite => "chess-2d-pieces/king_white.png",
+        PieceType::None => unreachable!("PieceType::None has no image"),
     }
 }
 
-impl PieceType {
-    fn get_value(&self) -> i8 {
-        match self {
-            PieceType::PawnWhite | PieceType::PawnBlack => 1,
-            PieceType::KnightWhite | PieceType::KnightBlack => 3,
-            PieceType::BishopWhite | PieceType::BishopBlack => 3,
-            PieceType::RookWhite | PieceType::RookBlack => 5,
-            PieceType::QueenWhite | PieceType::QueenBlack => 9,
-            _ => 0,
-        }
-    }
-}
-
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum PieceColor {
-    White,
-    Black,
-}
-
-#[derive(Clone, Copy, Component, Debug, Default, PartialEq)]
-struct Square {
-    x: u8,
-    y: u8,
-}
+/// Square of the chessboard that can be clicked
+#[derive(Clone, Copy, Component, Debug)]
+struct BoardSquare(Square);
 
+/// Sprite of a piece of the game's position<br />
+/// `direction` is the square the sprite is moving to
 #[derive(Clone, Copy, Component, Debug)]
-struct Piece {
+struct PieceSprite {
     piece_type: PieceType,
     color: PieceColor,
     square: Square,
     direction: Square,
 }
 
-impl Piece {
-    // Returns the possible moves for a piece
-    fn is_move_valid(&self, new_position: Square, pieces: &Vec<Piece>) -> bool {
-        // Checks if new position is same as current position
-        if new_position == self.square {
-            return false;
-        }
-
-        dbg!(&self);
-        dbg!(&new_position);
-        // If there's a piece of the same color in the new position, return false
-        if color_of_piece(new_position, pieces) == Some(self.color) {
-            return false;
-        }
-
-        match self.piece_type {
-            PieceType::KingWhite | PieceType::KingBlack => {
-                // King can move one square in any direction
-                // Horizontal
-                ((self.square.x as i8 - new_position.x as i8).abs() == 1
-                    && (self.square.y == new_position.y))
-                // Vertical
-                || ((self.square.y as i8 - new_position.y as i8).abs() == 1
-                && (self.square.x == new_position.x))
-                // Diagonal
-                || ((self.square.x as i8 - new_position.x as i8).abs() == 1
-                    && (self.square.y as i8 - new_position.y as i8).abs() == 1)
-            }
-
-            PieceType::QueenWhite | PieceType::QueenBlack => {
-                // Queen can move any number of squares in any direction, horizontally, vertically or diagonally
-                is_path_empty(self.square, new_position, pieces)
-                    && ((self.square.x as i8 - new_position.x as i8).abs()
-                        == (self.square.y as i8 - new_position.y as i8).abs()
-                        || ((self.square.x == new_position.x && self.square.y != new_position.y)
-                            || (self.square.x != new_position.x
-                                && self.square.y == new_position.y)))
-            }
-
-            PieceType::BishopWhite | PieceType::BishopBlack => {
-                // Bishop can move any number of squares diagonally
-                is_path_empty(self.square, new_position, pieces)
-                    && (self.square.x as i8 - new_position.x as i8).abs()
-                        == (self.square.y as i8 - new_position.y as i8).abs()
-            }
-
-            PieceType::KnightWhite | PieceType::KnightBlack => {
-                // Knight moves in an L shape
-                ((self.square.x as i8 - new_position.x as i8).abs() == 2
-                    && (self.square.y as i8 - new_position.y as i8).abs() == 1)
-                    || ((self.square.x as i8 - new_position.x as i8).abs() == 1
-                        && (self.square.y as i8 - new_position.y as i8).abs() == 2)
-            }
-
-            PieceType::RookWhite | PieceType::RookBlack => {
-                // Rook can move any number of squares horizontally or vertically
-                is_path_empty(self.square, new_position, pieces)
-                    && ((self.square.x == new_position.x && self.square.y != new_position.y)
-                        || (self.square.x != new_position.x && self.square.y == new_position.y))
-            }
+/// Directory played games are saved to as PGN
+const PGN_DIRECTORY: &str = "games";
 
-            PieceType::PawnWhite => {
-                // 1 Square forward
-                if new_position.y as i8 - self.square.y as i8 == 1
-                    && (self.square.x == new_position.x)
-                    && color_of_piece(new_position, pieces).is_none()
-                {
-                    return true;
-                }
+/// State of the game, sprites on the board only mirror its position
+#[derive(Debug, Resource)]
+struct Game {
+    start_position: Position,
+    position: Position,
+    /// Moves played from the start position
+    moves: Vec<Move>,
+    /// Hashes of all positions that occurred in the game, used for threefold repetition
+    position_history: Vec<u64>,
+    started_at: SystemTime,
+}
 
-                // 2 Squares forward
-                if self.square.y == 1
-                    && new_position.y as i8 - self.square.y as i8 == 2
-                    && (self.square.x == new_position.x)
-                    && is_path_empty(self.square, new_position, pieces)
-                    && color_of_piece(new_position, pieces).is_none()
-                {
-                    return true;
-                }
+impl Game {
+    fn new(position: Position) -> Self {
+        Self {
+            start_position: position.clone(),
+            // Starting position counts for threefold repetition too
+            position_history: vec![position.hash()],
+            position,
+            moves: Vec::new(),
+            started_at: SystemTime::now(),
+        }
+    }
 
-                // Take piece diagonally
-                if new_position.y as i8 - self.square.y as i8 == 1
-                    && (self.square.x as i8 - new_position.x as i8).abs() == 1
-                    && color_of_piece(new_position, pieces) == Some(PieceColor::Black)
-                {
-                    return true;
-                }
+    fn make_move(&mut self, mv: Move) {
+        info!("Move: {}", move_to_san(&self.position, mv));
+        self.position = self.position.make_move(mv);
+        self.moves.push(mv);
+        self.position_history.push(self.position.hash());
+    }
 
-                false
-            }
+    /// Returns the moves played so far in SAN
+    fn san_moves(&self) -> Vec<String> {
+        let mut position: Position = self.start_position.clone();
+        self.moves
+            .iter()
+            .map(|mv| {
+                let san: String = move_to_san(&position, *mv);
+                position = position.make_move(*mv);
+                san
+            })
+            .collect()
+    }
 
-            PieceType::PawnBlack => {
-                // 1 Square forward
-                if new_position.y as i8 - self.square.y as i8 == -1
-                    && (self.square.x == new_position.x)
-                    && color_of_piece(new_position, pieces).is_none()
-                {
-                    return true;
-                }
+    fn to_pgn(&self, result: Option<GameResult>) -> PgnGame {
+        let mut pgn: PgnGame = PgnGame::new(self.start_position.clone());
+        pgn.set_tag("Event", "Casual game");
+        pgn.set_tag("Site", WINDOW_TITLE);
+        pgn.set_tag("Date", &pgn_date(self.started_at));
+        pgn.set_tag("Result", result_token(result));
+        pgn.moves = self.moves.clone();
+        pgn
+    }
 
-                // 2 Squares forward
-                if self.square.y == 6
-                    && new_position.y as i8 - self.square.y as i8 == -2
-                    && (self.square.x == new_position.x)
-                    && is_path_empty(self.square, new_position, pieces)
-                    && color_of_piece(new_position, pieces).is_none()
-                {
-                    return true;
-                }
+    /// Writes the game as PGN to `PGN_DIRECTORY`, later saves of the same game overwrite the file
+    fn save_pgn(&self, result: Option<GameResult>) -> std::io::Result<PathBuf> {
+        let started_at: u64 = self
+            .started_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let path: PathBuf = Path::new(PGN_DIRECTORY).join(format!(
+            "{}_{}.pgn",
+            pgn_date(self.started_at),
+            started_at
+        ));
+
+        std::fs::create_dir_all(PGN_DIRECTORY)?;
+        std::fs::write(&path, self.to_pgn(result).to_pgn())?;
+        Ok(path)
+    }
+}
 
-                // Take piece diagonally
-                if new_position.y as i8 - self.square.y as i8 == -1
-                    && (self.square.x as i8 - new_position.x as i8).abs() == 1
-                    && color_of_piece(new_position, pieces) == Some(PieceColor::White)
-                {
-                    return true;
-                }
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(Position::new())
+    }
+}
 
-                false
-            }
+/// Returns the game to play, from the position given as `--fen <FEN>` or the starting position<br />
+/// Exits if the FEN is invalid
+fn game_from_args() -> Game {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(fen) = fen_from_args(&args) else {
+        return Game::default();
+    };
 
-            PieceType::None => unreachable!("PieceType::None is not a valid piece type"),
+    match Position::from_fen(&fen) {
+        Ok(position) => Game::new(position),
+        Err(error) => {
+            eprintln!("Invalid FEN \"{fen}\": {error}");
+            std::process::exit(1);
         }
     }
 }
 
+/// Returns the FEN after `--fen`, quoted as one argument or split into its six fields<br />
+/// An unquoted FEN ends at the next `--` option, so other options may follow it
+fn fen_from_args(args: &[String]) -> Option<String> {
+    let index: usize = args.iter().position(|arg| arg == "--fen")?;
+    let rest: &[String] = &args[index + 1..];
+    if rest.first().is_some_and(|arg| arg.contains(' ')) {
+        return Some(rest[0].clone());
+    }
+
+    let fields: Vec<&str> = rest
+        .iter()
+        .take(6)
+        .take_while(|arg| !arg.starts_with("--"))
+        .map(String::as_str)
+        .collect();
+    Some(fields.join(" "))
+}
+
 #[derive(Debug, Resource)]
 pub struct Turn {
     color: PieceColor,
@@ -390,10 +262,15 @@ pub struct Turn {
 }
 
 impl Turn {
-    fn new() -> Self {
+    /// Returns the turn of the player to move in the position, 1st is White's first move
+    fn new(position: &Position) -> Self {
         Self {
-            color: PieceColor::White,
-            n: 1,
+            color: position.color_to_move,
+            n: (position.fullmove_number - 1) * 2
+                + match position.color_to_move {
+                    PieceColor::White => 1,
+                    PieceColor::Black => 2,
+                },
         }
     }
 
@@ -406,10 +283,15 @@ impl Turn {
     }
 }
 
-impl Default for Turn {
-    fn default() -> Self {
-        Self::new()
-    }
+/// Holds the result once the game is over, no more moves are accepted after that
+#[derive(Debug, Default, Resource)]
+struct GameOver {
+    result: Option<GameResult>,
+}
+
+/// Sent once when the game ends
+struct GameOverEvent {
+    result: GameResult,
 }
 
 #[derive(Component)]
@@ -418,180 +300,67 @@ struct Captured;
 #[derive(Component)]
 struct RotateEachTurn;
 
-#[derive(Component)]
-struct Promoted;
+/// Pawn waiting for the player to choose a piece to promote to
+#[derive(Debug, Default, Resource)]
+struct PendingPromotion {
+    entity: Option<Entity>,
+    mv: Option<Move>,
+}
+
+/// Sent to play a legal move of the player to move, whether it was clicked, typed or chosen by the AI
+struct MoveEvent {
+    mv: Move,
+}
+
+/// Move typed in SAN by the player, played with Enter
+#[derive(Debug, Default, Resource)]
+pub struct SanInput(pub String);
+
+/// Sent when a move is finished and the other player should be on turn
+struct TurnEndEvent;
 
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    game: Res<Game>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
+    // Board is seen from the side of the player to move, like after each turn
+    let rotation: Quat = board_rotation(game.position.color_to_move);
+
     // Camera
+    let mut camera: Camera2dBundle = Camera2dBundle::default();
+    camera.transform.rotation = rotation;
     commands
-        .spawn(Camera2dBundle::default())
+        .spawn(camera)
         .insert(PickingCameraBundle::default())
         .insert(RotateEachTurn);
 
-    // Pieces
-    macro_rules! load_piece {
-        ($piece:ident) => {
-            let $piece: Handle<Image> =
-                asset_server.load(format!("chess-2d-pieces/{}.png", stringify!($piece)));
-        };
-    }
-
-    load_piece!(pawn_black);
-    load_piece!(pawn_white);
-    load_piece!(rook_black);
-    load_piece!(rook_white);
-    load_piece!(knight_black);
-    load_piece!(knight_white);
-    load_piece!(bishop_black);
-    load_piece!(bishop_white);
-    load_piece!(queen_black);
-    load_piece!(queen_white);
-    load_piece!(king_black);
-    load_piece!(king_white);
-
-    // Array of piece positions
-    // Starts from the bottom left corner
-    // Array is row
-    // Nested array is column
-    let piece_positions: [[PieceType; 8]; 8] = [
-        [
-            PieceType::RookWhite,
-            PieceType::PawnWhite,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::PawnBlack,
-            PieceType::RookBlack,
-        ],
-        [
-            PieceType::KnightWhite,
-            PieceType::PawnWhite,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::PawnBlack,
-            PieceType::KnightBlack,
-        ],
-        [
-            PieceType::BishopWhite,
-            PieceType::PawnWhite,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::PawnBlack,
-            PieceType::BishopBlack,
-        ],
-        [
-            PieceType::QueenWhite,
-            PieceType::PawnWhite,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::PawnBlack,
-            PieceType::QueenBlack,
-        ],
-        [
-            PieceType::KingWhite,
-            PieceType::PawnWhite,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::PawnBlack,
-            PieceType::KingBlack,
-        ],
-        [
-            PieceType::BishopWhite,
-            PieceType::PawnWhite,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::PawnBlack,
-            PieceType::BishopBlack,
-        ],
-        [
-            PieceType::KnightWhite,
-            PieceType::PawnWhite,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::PawnBlack,
-            PieceType::KnightBlack,
-        ],
-        [
-            PieceType::RookWhite,
-            PieceType::PawnWhite,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::None,
-            PieceType::PawnBlack,
-            PieceType::RookBlack,
-        ],
-    ];
-
     // Chessboard
     let black_material: Handle<ColorMaterial> =
         materials.add(ColorMaterial::from(Color::rgb(0.0, 0.0, 0.0)));
     let white_material: Handle<ColorMaterial> =
         materials.add(ColorMaterial::from(Color::rgb(1.0, 1.0, 1.0)));
 
-    let n_of_squares: u8 = 8;
-    let square_size: f32 = 60.0;
-    let piece_size: f32 = 0.06;
-
-    let board_half_width = square_size * n_of_squares as f32 / 2.0;
-    let piece_scale: Vec3 = Vec3::new(piece_size, piece_size, 1.);
     let square_mesh: Mesh2dHandle = meshes.add(Mesh::from(shape::Quad::default())).into();
 
-    for column in 0..n_of_squares {
-        for row in 0..n_of_squares {
-            let piece_type = &piece_positions[column as usize][row as usize];
-            let square_pos = Vec3::new(
-                column as f32 * square_size - board_half_width + square_size / 2.0,
-                row as f32 * square_size - board_half_width + square_size / 2.0,
-                0.,
-            );
+    for column in 0..N_OF_SQUARES {
+        for row in 0..N_OF_SQUARES {
+            let square: Square = Square { x: column, y: row };
             let material = if (column + row) % 2 == 0 {
                 &black_material
             } else {
                 &white_material
             };
 
-            let piece_color: Option<PieceColor> = match piece_type {
-                PieceType::PawnBlack
-                | PieceType::RookBlack
-                | PieceType::KnightBlack
-                | PieceType::BishopBlack
-                | PieceType::QueenBlack
-                | PieceType::KingBlack => Some(PieceColor::Black),
-                PieceType::PawnWhite
-                | PieceType::RookWhite
-                | PieceType::KnightWhite
-                | PieceType::BishopWhite
-                | PieceType::QueenWhite
-                | PieceType::KingWhite => Some(PieceColor::White),
-                PieceType::None => None,
-            };
-
             // Spawn square
             commands
                 .spawn((
                     MaterialMesh2dBundle {
                         transform: Transform {
-                            translation: square_pos,
-                            scale: Vec3::new(square_size, square_size, 1.),
+                            translation: square_translation(square),
+                            scale: Vec3::new(SQUARE_SIZE, SQUARE_SIZE, 1.),
                             ..default()
                         },
                         material: material.clone(),
@@ -600,44 +369,61 @@ fn setup(
                     },
                     PickableBundle::default(),
                 ))
-                .insert(Square { x: column, y: row });
-
-            // Spawn piece
-            commands
-                .spawn(SpriteBundle {
-                    transform: Transform {
-                        translation: square_pos,
-                        scale: piece_scale,
-                        ..default()
-                    },
-                    texture: match piece_type {
-                        PieceType::PawnBlack => pawn_black.clone(),
-                        PieceType::PawnWhite => pawn_white.clone(),
-                        PieceType::RookBlack => rook_black.clone(),
-                        PieceType::RookWhite => rook_white.clone(),
-                        PieceType::KnightBlack => knight_black.clone(),
-                        PieceType::KnightWhite => knight_white.clone(),
-                        PieceType::BishopBlack => bishop_black.clone(),
-                        PieceType::BishopWhite => bishop_white.clone(),
-                        PieceType::QueenBlack => queen_black.clone(),
-                        PieceType::QueenWhite => queen_white.clone(),
-                        PieceType::KingBlack => king_black.clone(),
-                        PieceType::KingWhite => king_white.clone(),
-                        PieceType::None => continue,
-                    },
-                    ..default()
-                })
-                .insert(Piece {
-                    piece_type: *piece_type,
-                    color: piece_color.expect(
-                        "Piece should have a color, PieceType::None is skipped in the loop",
-                    ),
-                    square: { Square { x: column, y: row } },
-                    direction: { Square { x: column, y: row } },
-                })
-                .insert(RotateEachTurn);
+                .insert(BoardSquare(square));
         }
     }
+
+    // Spawn pieces
+    for piece in game.position.pieces() {
+        spawn_piece_sprite(&mut commands, &asset_server, piece, rotation);
+    }
+}
+
+const N_OF_SQUARES: u8 = 8;
+const SQUARE_SIZE: f32 = 60.0;
+const PIECE_SIZE: f32 = 0.06;
+
+/// Returns the rotation of the camera and pieces when the given player is to move
+fn board_rotation(color: PieceColor) -> Quat {
+    match color {
+        PieceColor::White => Quat::IDENTITY,
+        PieceColor::Black => Quat::from_rotation_z(std::f32::consts::PI),
+    }
+}
+
+/// Returns the position of the center of the square in the world
+fn square_translation(square: Square) -> Vec3 {
+    let board_half_width: f32 = SQUARE_SIZE * N_OF_SQUARES as f32 / 2.0;
+    Vec3::new(
+        square.x as f32 * SQUARE_SIZE - board_half_width + SQUARE_SIZE / 2.0,
+        square.y as f32 * SQUARE_SIZE - board_half_width + SQUARE_SIZE / 2.0,
+        0.,
+    )
+}
+
+fn spawn_piece_sprite(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    piece: Piece,
+    rotation: Quat,
+) {
+    commands
+        .spawn(SpriteBundle {
+            transform: Transform {
+                translation: square_translation(piece.square),
+                rotation,
+                scale: Vec3::new(PIECE_SIZE, PIECE_SIZE, 1.),
+            },
+            texture: asset_server.load(get_image_path(piece.piece_type)),
+            ..default()
+        })
+        .insert(PieceSprite {
+            piece_type: piece.piece_type,
+            color: piece.color,
+            square: piece.square,
+            direction: piece.square,
+        })
+        .insert(RotateEachTurn);
 }
 
 fn select_square(
@@ -645,7 +431,7 @@ fn select_square(
     mouse_button_inputs: Res<Input<MouseButton>>,
     mut selected_square: ResMut<SelectedSquare>,
     // mut selected_piece: ResMut<SelectedPiece>,
-    squares_query: Query<&Square>,
+    squares_query: Query<&BoardSquare>,
 ) {
     // Check if mouse is clicked
     if !mouse_button_inputs.just_pressed(MouseButton::Left) {
@@ -675,19 +461,27 @@ fn select_square(
     }
 }
 
+/// Turns the selected piece and square into a move if it's legal
 #[allow(clippy::too_many_arguments)]
 fn get_piece_for_move(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    audio: Res<bevy_kira_audio::Audio>,
     mut selected_square: ResMut<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
-    mut turn: ResMut<Turn>,
-    squares_query: Query<&Square>,
-    mut pieces_query: Query<(Entity, &mut Piece)>,
-    mut rotation_query: Query<&mut Transform, With<RotateEachTurn>>,
+    pending_promotion: Res<PendingPromotion>,
+    mut move_events: EventWriter<MoveEvent>,
+    game: Res<Game>,
+    game_over: Res<GameOver>,
+    replay: Res<Replay>,
+    ai_search: Res<AISearch>,
+    squares_query: Query<&BoardSquare>,
+    pieces_query: Query<&PieceSprite>,
 ) {
-    if !selected_square.is_changed() {
+    // Player waits while the AI thinks
+    if !selected_square.is_changed()
+        || game_over.result.is_some()
+        || pending_promotion.entity.is_some()
+        || replay.is_active()
+        || ai_search.is_thinking()
+    {
         return;
     }
 
@@ -698,106 +492,228 @@ fn get_piece_for_move(
     };
 
     let square: &Square = if let Ok(square) = squares_query.get(square_entity) {
-        square
+        &square.0
     } else {
         return;
     };
 
     if let Some(selected_piece_entity) = selected_piece.entity {
-        let pieces_vec: Vec<Piece> = pieces_query.iter_mut().map(|(_, piece)| *piece).collect();
-        let pieces_entity_vec: Vec<(Entity, Piece)> = pieces_query
-            .iter_mut()
-            .map(|(entity, piece)| (entity, *piece))
-            .collect::<Vec<(Entity, Piece)>>();
-
-        // Move the selected piece to selected square
-        let (entity, mut piece): (Entity, Mut<Piece>) =
-            if let Ok((entity, piece)) = pieces_query.get_mut(selected_piece_entity) {
-                (entity, piece)
-            } else {
-                return;
-            };
+        let piece: &PieceSprite = if let Ok(piece) = pieces_query.get(selected_piece_entity) {
+            piece
+        } else {
+            return;
+        };
 
         info!("Piece selected: {:?}", piece.piece_type);
-        info!("Square selected: {:?}", square);
-
-        if !piece.is_move_valid(*square, &pieces_vec) {
+        info!("Square selected: {}", square);
+
+        if let Some(mv) = generate_legal_moves(&game.position)
+            .into_iter()
+            .find(|mv| mv.from == piece.square && mv.to == *square)
+        {
+            // Player chooses the piece a pawn promotes to after it reaches the last row
+            move_events.send(MoveEvent {
+                mv: Move {
+                    promotion: None,
+                    ..mv
+                },
+            });
+        } else if game.position.is_move_valid(piece.square, *square) {
+            // Moves that leave own king in check are illegal
+            warn!("Move not valid, {:?} king would be in check", piece.color);
+        } else {
             warn!("Move not valid");
-            return;
         }
 
-        // Check if pawn is on the last row and insert it Promoted component
-        // Black
-        if piece.piece_type == PieceType::PawnBlack && square.y == 0 {
-            piece.piece_type = PieceType::QueenBlack;
-            commands.entity(entity).insert(Promoted);
-        // White
-        } else if piece.piece_type == PieceType::PawnWhite && square.y == 7 {
-            piece.piece_type = PieceType::QueenWhite;
-            commands.entity(entity).insert(Promoted);
+        // Deselect piece
+        selected_square.entity = None;
+        selected_piece.entity = None;
+    }
+}
+
+/// Lets the player type moves in SAN, e.g. `Nbd7` or `exd6`<br />
+/// Enter plays the typed move, Backspace deletes a character and Escape clears the input
+#[allow(clippy::too_many_arguments)]
+fn type_san_move(
+    mut char_events: EventReader<ReceivedCharacter>,
+    keyboard_inputs: Res<Input<KeyCode>>,
+    mut san_input: ResMut<SanInput>,
+    mut move_events: EventWriter<MoveEvent>,
+    game: Res<Game>,
+    game_over: Res<GameOver>,
+    pending_promotion: Res<PendingPromotion>,
+    replay: Res<Replay>,
+    ai_search: Res<AISearch>,
+) {
+    if game_over.result.is_some()
+        || pending_promotion.entity.is_some()
+        || replay.is_active()
+        || ai_search.is_thinking()
+    {
+        char_events.clear();
+        return;
+    }
+
+    for event in char_events.iter() {
+        if event.char.is_ascii_alphanumeric() || "=+#-".contains(event.char) {
+            san_input.0.push(event.char);
+        }
+    }
+
+    if keyboard_inputs.just_pressed(KeyCode::Back) {
+        san_input.0.pop();
+    } else if keyboard_inputs.just_pressed(KeyCode::Escape) {
+        san_input.0.clear();
+    } else if keyboard_inputs.just_pressed(KeyCode::Return) && !san_input.0.is_empty() {
+        match san_to_move(&game.position, &san_input.0) {
+            Ok(mv) => move_events.send(MoveEvent { mv }),
+            Err(error) => warn!("{}", error),
         }
+        san_input.0.clear();
+    }
+}
+
+/// Plays a legal move on the board: moves the sprites, marks captured pieces,
+/// plays the sound and passes the turn<br />
+/// Pawns reaching the last row without a promotion piece wait for the player to choose one
+#[allow(clippy::too_many_arguments)]
+fn play_move(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    audio: Res<bevy_kira_audio::Audio>,
+    mut move_events: EventReader<MoveEvent>,
+    mut pending_promotion: ResMut<PendingPromotion>,
+    mut turn_end_events: EventWriter<TurnEndEvent>,
+    mut game: ResMut<Game>,
+    mut pieces_query: Query<(Entity, &mut PieceSprite, &mut Handle<Image>)>,
+) {
+    for move_event in move_events.iter() {
+        let mv: Move = move_event.mv;
+        let Some(piece) = game.position.piece_at(mv.from) else {
+            warn!("No piece to move on {}", mv.from);
+            continue;
+        };
 
-        // Check if piece of the opposite color exists in this square and remove it
-        info!("Move valid");
+        // King moving two squares is castling, the rook jumps over the king
+        let castling_rook: Option<(Square, Square)> =
+            if piece.piece_type.is_king() && (mv.from.x as i8 - mv.to.x as i8).abs() == 2 {
+                let (rook_x, rook_new_x): (u8, u8) = if mv.to.x == 6 { (7, 5) } else { (0, 3) };
+                info!("Castling");
+                Some((
+                    Square {
+                        x: rook_x,
+                        y: mv.to.y,
+                    },
+                    Square {
+                        x: rook_new_x,
+                        y: mv.to.y,
+                    },
+                ))
+            } else {
+                None
+            };
+
+        // Pawn moving diagonally to the en passant square captures the pawn beside it
+        let captured_square: Square =
+            if piece.piece_type.is_pawn() && game.position.en_passant_square == Some(mv.to) {
+                info!("En passant");
+                Square {
+                    x: mv.to.x,
+                    y: mv.from.y,
+                }
+            } else {
+                mv.to
+            };
+
+        let is_promotion: bool = piece.piece_type.is_pawn() && (mv.to.y == 0 || mv.to.y == 7);
         let mut captured: bool = false;
-        for (other_entity, other_piece) in pieces_entity_vec {
-            if other_piece.square == *square && other_piece.color != piece.color {
+        for (entity, mut sprite, mut texture) in pieces_query.iter_mut() {
+            if sprite.square == mv.from {
+                // Set direction for piece to move to
+                sprite.direction = mv.to;
+
+                if let Some(promotion) = mv.promotion {
+                    info!("Promoted piece to {:?}", promotion);
+                    sprite.piece_type = promotion;
+                    *texture = asset_server.load(get_image_path(promotion));
+                } else if is_promotion {
+                    // Turn doesn't change until the player chooses a piece to promote to
+                    info!("Choose a piece to promote to");
+                    pending_promotion.entity = Some(entity);
+                    pending_promotion.mv = Some(mv);
+                }
+            } else if sprite.square == captured_square && sprite.color != piece.color {
                 // Mark piece as captured
-                commands.entity(other_entity).insert(Captured);
-                dbg!(other_entity);
-                dbg!(other_piece.square);
-
-                // Play capture sound
-                let sound: Handle<AudioSource> = asset_server.load("sounds/capture.mp3");
-                audio.play(sound);
+                commands.entity(entity).insert(Captured);
                 captured = true;
+            } else if let Some((rook_square, rook_new_square)) = castling_rook {
+                // Move the rook alongside the king when castling
+                if sprite.square == rook_square {
+                    sprite.direction = rook_new_square;
+                }
             }
         }
 
-        if !captured {
-            // Play move sound
-            let sound: Handle<AudioSource> = asset_server.load("sounds/move.mp3");
-            audio.play(sound);
-        }
-
-        dbg!(&piece);
-        dbg!(&turn);
+        // Play capture or move sound
+        let sound: Handle<AudioSource> = if captured {
+            asset_server.load("sounds/capture.mp3")
+        } else {
+            asset_server.load("sounds/move.mp3")
+        };
+        audio.play(sound);
 
-        // Continue only if it's the turn of the piece's color
-        if piece.color != turn.color {
-            // Deselect piece
-            warn!("It's not {:?}'s turn", piece.color);
-            selected_square.entity = None;
-            selected_piece.entity = None;
-            return;
+        if mv.promotion.is_some() || !is_promotion {
+            game.make_move(mv);
+            turn_end_events.send(TurnEndEvent);
         }
+    }
+}
 
-        // Set direction for piece to move to
-        piece.direction = *square;
-        dbg!(piece);
-        dbg!(square);
+/// Passes the turn to the other player once a move is finished
+/// and checks whether the game is over<br />
+/// Runs after captured pieces are marked, so the UI sees them when the turn changes
+fn end_turn(
+    mut turn_end_events: EventReader<TurnEndEvent>,
+    mut turn: ResMut<Turn>,
+    game: Res<Game>,
+    mut game_over: ResMut<GameOver>,
+    mut game_over_events: EventWriter<GameOverEvent>,
+    mut rotation_query: Query<&mut Transform, With<RotateEachTurn>>,
+) {
+    if turn_end_events.iter().count() == 0 {
+        return;
+    }
 
-        // Change turn
-        turn.next();
+    let position: &Position = &game.position;
+    if position.is_in_check(position.color_to_move) {
+        info!("{:?} is in check", position.color_to_move);
+    }
 
-        // Rotate camera and all pieces after each turn
-        for mut rotation in rotation_query.iter_mut() {
-            rotation.rotate(Quat::from_rotation_z(std::f32::consts::PI));
-        }
+    // Change turn
+    turn.next();
 
-        info!(
-            "It's {:?}'s turn and it's {} turn",
-            turn.color,
-            Ordinal(turn.n)
-        );
+    // Rotate camera and all pieces after each turn
+    for mut rotation in rotation_query.iter_mut() {
+        rotation.rotate(Quat::from_rotation_z(std::f32::consts::PI));
+    }
 
-        // Deselect piece
-        selected_square.entity = None;
-        selected_piece.entity = None;
+    info!(
+        "It's {:?}'s turn and it's {} turn",
+        turn.color,
+        Ordinal(turn.n)
+    );
+
+    // Check if the player to move has any legal move left or the game is drawn
+    if let Some(result) =
+        get_game_result(position).or_else(|| get_draw_result(position, &game.position_history))
+    {
+        info!("Game over: {:?}", result);
+        game_over.result = Some(result);
+        game_over_events.send(GameOverEvent { result });
     }
 }
 
-fn move_piece(mut query: Query<(&mut Transform, &mut Piece)>) {
+fn move_piece(mut query: Query<(&mut Transform, &mut PieceSprite)>) {
     for (mut transform, mut piece) in query.iter_mut() {
         // Get direction to move to
         // direction = where to move - where we are
@@ -805,7 +721,7 @@ fn move_piece(mut query: Query<(&mut Transform, &mut Piece)>) {
         let where_is_piece: Vec3 = Vec3::new(piece.square.x as f32, piece.square.y as f32, 0.);
         let direction: Vec3 = where_to_move - where_is_piece;
 
-        let square_size: Vec3 = Vec3::new(60., 60., 0.);
+        let square_size: Vec3 = Vec3::new(SQUARE_SIZE, SQUARE_SIZE, 0.);
 
         // Only move if piece isn't already here
         if direction.length() > 0.1 {
@@ -823,8 +739,8 @@ fn select_piece(
     selected_square: Res<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
     turn: Res<Turn>,
-    squares_query: Query<&Square>,
-    pieces_query: Query<(Entity, &Piece)>,
+    squares_query: Query<&BoardSquare>,
+    pieces_query: Query<(Entity, &PieceSprite)>,
 ) {
     if !selected_square.is_changed() {
         return;
@@ -839,7 +755,7 @@ fn select_piece(
     };
 
     let square: &Square = if let Ok(square) = squares_query.get(square_entity) {
-        square
+        &square.0
     } else {
         return;
     };
@@ -855,161 +771,63 @@ fn select_piece(
     }
 }
 
-/// Returns the color of the piece at the given position<br />
-/// Returns None if there is no piece at the given position
-fn color_of_piece(pos: Square, pieces: &Vec<Piece>) -> Option<PieceColor> {
-    for piece in pieces {
-        if piece.square == pos {
-            return Some(piece.color);
-        }
-    }
-
-    None
-}
-
-fn is_path_empty(start: Square, end: Square, pieces: &Vec<Piece>) -> bool {
-    // Same column
-    if start.x == end.x {
-        for piece in pieces {
-            if piece.square.x == start.x
-                && ((piece.square.y > start.y && piece.square.y < end.y)
-                    || (piece.square.y > end.y && piece.square.y < start.y))
-            {
-                return false;
-            }
-        }
-    }
-
-    // Same row
-    if start.y == end.y {
-        for piece in pieces {
-            if piece.square.y == start.y
-                && ((piece.square.x > start.x && piece.square.x < end.x)
-                    || (piece.square.x > end.x && piece.square.x < start.x))
-            {
-                return false;
-            }
-        }
-    }
-
-    // Diagonals
-    let x_diff = (start.x as i8 - end.x as i8).abs();
-    let y_diff = (start.y as i8 - end.y as i8).abs();
-
-    if x_diff == y_diff {
-        for i in 1..x_diff {
-            let pos: Square = if start.x < end.x && start.y < end.y {
-                // Piece => Top right
-                Square {
-                    x: start.x + i as u8,
-                    y: start.y + i as u8,
-                }
-            } else if start.x < end.x && start.y > end.y {
-                // Piece => Bottom right
-                Square {
-                    x: start.x + i as u8,
-                    y: start.y - i as u8,
-                }
-            } else if start.x > end.x && start.y < end.y {
-                // Piece => Top left
-                Square {
-                    x: start.x - i as u8,
-                    y: start.y + i as u8,
-                }
-            } else {
-                // Piece => Bottom left
-                Square {
-                    x: start.x - i as u8,
-                    y: start.y - i as u8,
-                }
-            };
-
-            if color_of_piece(pos, pieces).is_some() {
-                return false;
-            }
-        }
-    }
-
-    true
-}
-
-fn despawn_captured_pieces(
-    mut commands: Commands,
-    mut app_exit_events: EventWriter<AppExit>,
-    query: Query<(Entity, &Piece, With<Captured>)>,
-) {
-    for (entity, piece, _) in query.iter() {
+fn despawn_captured_pieces(mut commands: Commands, query: Query<Entity, With<Captured>>) {
+    for entity in query.iter() {
         info!("Despawn captured piece: {:?}", entity.index());
-        if piece.piece_type == PieceType::KingWhite || piece.piece_type == PieceType::KingBlack {
-            println!("Thanks for playing!");
-            println!(
-                "{} won!",
-                match piece.color {
-                    PieceColor::White => "Black",
-                    PieceColor::Black => "White",
-                }
-            );
-            app_exit_events.send(AppExit);
-        }
 
         // Despawn captured piece
         commands.entity(entity).despawn_recursive();
     }
 }
 
-fn promote_pieces(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    turn: Res<Turn>,
-    mut pieces_query: Query<(&mut Handle<Image>, Entity), With<Promoted>>,
-) {
-    if !turn.is_changed() {
-        return;
-    }
-
-    // Load queen image
-    match turn.color {
-        PieceColor::White => {
-            let piece = pieces_query.get_single_mut();
-            if let Ok((mut piece, entity)) = piece {
-                info!("Promoted piece!");
-                *piece = asset_server.load("chess-2d-pieces/queen_white.png");
-                commands.get_entity(entity).unwrap().remove::<Promoted>();
-            }
-        }
-        PieceColor::Black => {
-            let piece = pieces_query.get_single_mut();
-            if let Ok((mut piece, entity)) = piece {
-                info!("Promoted piece!");
-                *piece = asset_server.load("chess-2d-pieces/queen_black.png");
-                commands.get_entity(entity).unwrap().remove::<Promoted>();
-            }
+/// Saves the finished game as PGN
+fn save_pgn_at_game_over(mut game_over_events: EventReader<GameOverEvent>, game: Res<Game>) {
+    for game_over_event in game_over_events.iter() {
+        match game.save_pgn(Some(game_over_event.result)) {
+            Ok(path) => info!("Game saved to {}", path.display()),
+            Err(error) => warn!("Couldn't save game: {}", error),
         }
     }
 }
 
-fn get_pieces(
-    ai_enabled_query: Query<&AIEnabled, With<AIEnabled>>,
-    pieces_query: Query<&Piece>,
-    turn: Res<Turn>,
-) {
-    if !turn.is_changed() {
-        return;
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let ai_enabled = ai_enabled_query.get_single().unwrap();
-    if !ai_enabled.0 {
-        return;
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
     }
 
-    let mut pieces_pos: [[PieceType; 8]; 8] = Default::default();
+    #[test]
+    fn reads_fen_from_args() {
+        let fen: &str = "8/8/8/4k3/8/8/8/4K2R w K - 0 1";
+        assert_eq!(fen_from_args(&args(&["chess-rs"])), None);
+        // Quoted
+        assert_eq!(
+            fen_from_args(&args(&["chess-rs", "--fen", fen, "--pgn", "x.pgn"])),
+            Some(fen.to_string())
+        );
 
-    for piece in pieces_query.iter() {
-        pieces_pos[(-(piece.square.y as i8) + 7) as usize][piece.square.x as usize] =
-            piece.piece_type;
+        // Split on its spaces, with options before and after
+        let mut split: Vec<&str> = vec!["chess-rs", "--game", "2", "--fen"];
+        split.extend(fen.split(' '));
+        split.extend(["--pgn", "x.pgn"]);
+        assert_eq!(fen_from_args(&args(&split)), Some(fen.to_string()));
+
+        // Fields left out stop at the next option, the FEN is then invalid
+        assert_eq!(
+            fen_from_args(&args(&[
+                "chess-rs",
+                "--fen",
+                "8/8/8/4k3/8/8/8/4K2R",
+                "w",
+                "--pgn"
+            ])),
+            Some("8/8/8/4k3/8/8/8/4K2R w".to_string())
+        );
+        assert_eq!(
+            fen_from_args(&args(&["chess-rs", "--fen"])),
+            Some(String::new())
+        );
     }
-
-    dbg!(pieces_pos);
-
-    println!("Fen: {}", pieces_pos.to_fen(turn));
 }
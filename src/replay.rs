@@ -0,0 +1,334 @@
+use crate::*;
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayStep>()
+            .add_startup_system(init_replay_ui)
+            .add_system(replay_buttons_ui)
+            .add_system(step_replay.before(move_piece))
+            .add_system(update_replay_ui.after(step_replay));
+    }
+}
+
+/// Game loaded from PGN whose mainline is stepped through instead of played<br />
+/// Players can't make moves while a replay is active
+#[derive(Debug, Default, Resource)]
+pub struct Replay {
+    pgn: Option<PgnGame>,
+    /// Position after each move of the mainline, the start position first
+    positions: Vec<Position>,
+    /// Number of moves currently shown on the board
+    index: usize,
+}
+
+impl Replay {
+    pub fn new(pgn: PgnGame) -> Self {
+        let mut positions: Vec<Position> = vec![pgn.start_position.clone()];
+        for mv in &pgn.moves {
+            let position: Position = positions[positions.len() - 1].make_move(*mv);
+            positions.push(position);
+        }
+
+        Self {
+            pgn: Some(pgn),
+            positions,
+            index: 0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.pgn.is_some()
+    }
+
+    /// Returns the game to show on the board before any move is replayed
+    pub fn start_game(&self) -> Option<Game> {
+        self.pgn
+            .as_ref()
+            .map(|pgn| Game::new(pgn.start_position.clone()))
+    }
+}
+
+/// Returns the replay of the game loaded with `--pgn <file> [--game <n>]`, games are counted from 1<br />
+/// Exits if the file can't be read or doesn't have the game
+pub fn replay_from_args() -> Replay {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(index) = args.iter().position(|arg| arg == "--pgn") else {
+        return Replay::default();
+    };
+
+    let exit = |message: String| -> ! {
+        eprintln!("{message}");
+        std::process::exit(1);
+    };
+
+    let Some(path) = args.get(index + 1) else {
+        exit("Missing PGN file after --pgn".to_string());
+    };
+    let game_number: usize = match args.iter().position(|arg| arg == "--game") {
+        Some(index) => match args.get(index + 1).and_then(|n| n.parse().ok()) {
+            Some(n) if n >= 1 => n,
+            _ => exit("--game needs a number from 1".to_string()),
+        },
+        None => 1,
+    };
+
+    let text: String = std::fs::read_to_string(path)
+        .unwrap_or_else(|error| exit(format!("Couldn't read {path}: {error}")));
+    let mut games: Vec<PgnGame> =
+        parse_pgn(&text).unwrap_or_else(|error| exit(format!("Invalid PGN {path}: {error}")));
+    if game_number > games.len() {
+        exit(format!(
+            "{path} has {} games, can't replay game {game_number}",
+            games.len()
+        ));
+    }
+
+    Replay::new(games.swap_remove(game_number - 1))
+}
+
+#[derive(Component)]
+struct ReplayText;
+
+/// Button stepping the replay by the given number of moves
+#[derive(Component)]
+struct ReplayButton(i8);
+
+/// Step requested by the replay buttons this frame
+#[derive(Debug, Default, Resource)]
+struct ReplayStep(i8);
+
+const REPLAY_BUTTON: BackgroundColor = BackgroundColor(Color::rgb(0.15, 0.15, 0.15));
+const REPLAY_BUTTON_HOVER: BackgroundColor = BackgroundColor(Color::rgb(0.25, 0.25, 0.25));
+
+fn init_replay_ui(mut commands: Commands, asset_server: ResMut<AssetServer>, replay: Res<Replay>) {
+    let Some(pgn) = &replay.pgn else {
+        return;
+    };
+    info!(
+        "Replaying {} - {}, {} moves",
+        pgn.tag("White").unwrap_or("?"),
+        pgn.tag("Black").unwrap_or("?"),
+        pgn.moves.len()
+    );
+
+    let font: Handle<Font> = asset_server.load("fonts/UbuntuMonoNerdFontCompleteMono.ttf");
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(35.0),
+                    left: Val::Px(5.0),
+                    ..default()
+                },
+                align_items: AlignItems::Center,
+                gap: Size::width(Val::Px(5.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for (label, step) in [("<", -1), (">", 1)] {
+                parent
+                    .spawn(ButtonBundle {
+                        background_color: REPLAY_BUTTON,
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 30.0,
+                                color: Color::WHITE,
+                            },
+                        ));
+                    })
+                    .insert(ReplayButton(step));
+            }
+
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(ReplayText);
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn replay_buttons_ui(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &ReplayButton),
+        Changed<Interaction>,
+    >,
+    mut replay_step: ResMut<ReplayStep>,
+) {
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => replay_step.0 = button.0,
+            Interaction::Hovered => color.0 = REPLAY_BUTTON_HOVER.0,
+            Interaction::None => color.0 = REPLAY_BUTTON.0,
+        }
+    }
+}
+
+/// Shows the replayed move, e.g. `Move 12/85: 6... b5`
+fn update_replay_ui(replay: Res<Replay>, mut query: Query<&mut Text, With<ReplayText>>) {
+    if !replay.is_changed() {
+        return;
+    }
+
+    let Some(pgn) = &replay.pgn else {
+        return;
+    };
+
+    let last_move: String = match replay.index.checked_sub(1) {
+        Some(last) => {
+            let position: &Position = &replay.positions[last];
+            let dots: &str = match position.color_to_move {
+                PieceColor::White => ".",
+                PieceColor::Black => "...",
+            };
+            format!(
+                ": {}{dots} {}",
+                position.fullmove_number,
+                move_to_san(position, pgn.moves[last])
+            )
+        }
+        None => String::new(),
+    };
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("Move {}/{}{last_move}", replay.index, pgn.moves.len());
+    }
+}
+
+/// Steps the replay with the arrow keys or the replay buttons<br />
+/// Sprites of moved pieces get a new direction and move like in a played game,
+/// captured pieces are despawned going forward and spawned again going back
+#[allow(clippy::too_many_arguments)]
+fn step_replay(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    keyboard_inputs: Res<Input<KeyCode>>,
+    mut replay_step: ResMut<ReplayStep>,
+    mut replay: ResMut<Replay>,
+    mut game: ResMut<Game>,
+    mut turn: ResMut<Turn>,
+    mut pieces_query: Query<(Entity, &mut PieceSprite, &mut Handle<Image>)>,
+) {
+    let step: i8 = if keyboard_inputs.just_pressed(KeyCode::Right) {
+        1
+    } else if keyboard_inputs.just_pressed(KeyCode::Left) {
+        -1
+    } else {
+        replay_step.0
+    };
+    replay_step.0 = 0;
+
+    let n_of_moves: usize = replay.pgn.as_ref().map_or(0, |pgn| pgn.moves.len());
+    let is_forward: bool = match step {
+        1 if replay.index < n_of_moves => true,
+        -1 if replay.index > 0 => false,
+        _ => return,
+    };
+
+    let Some(pgn) = replay.pgn.clone() else {
+        return;
+    };
+
+    // Move between the positions before and after it, whichever way the replay goes
+    let index: usize = if is_forward {
+        replay.index
+    } else {
+        replay.index - 1
+    };
+    let mv: Move = pgn.moves[index];
+    let before: &Position = &replay.positions[index];
+    let after: &Position = &replay.positions[index + 1];
+
+    let moving_piece: PieceType = before.board.piece_at(mv.from);
+    let captured_square: Square =
+        if moving_piece.is_pawn() && before.en_passant_square == Some(mv.to) {
+            Square {
+                x: mv.to.x,
+                y: mv.from.y,
+            }
+        } else {
+            mv.to
+        };
+    let captured_piece: Option<Piece> = before.piece_at(captured_square);
+
+    // King moving two squares is castling, the rook jumps over the king
+    let castling_rook: Option<(Square, Square)> =
+        if moving_piece.is_king() && (mv.from.x as i8 - mv.to.x as i8).abs() == 2 {
+            let (rook_x, rook_new_x): (u8, u8) = if mv.to.x == 6 { (7, 5) } else { (0, 3) };
+            Some((
+                Square {
+                    x: rook_x,
+                    y: mv.from.y,
+                },
+                Square {
+                    x: rook_new_x,
+                    y: mv.from.y,
+                },
+            ))
+        } else {
+            None
+        };
+
+    let (from, to): (Square, Square) = if is_forward {
+        (mv.from, mv.to)
+    } else {
+        (mv.to, mv.from)
+    };
+
+    for (entity, mut piece, mut texture) in pieces_query.iter_mut() {
+        if piece.square == from {
+            // Promoted pieces turn back into pawns when going back
+            let piece_type: PieceType = if is_forward {
+                after.board.piece_at(mv.to)
+            } else {
+                moving_piece
+            };
+            if piece.piece_type != piece_type {
+                piece.piece_type = piece_type;
+                *texture = asset_server.load(get_image_path(piece_type));
+            }
+            piece.direction = to;
+        } else if is_forward && piece.square == captured_square {
+            commands.entity(entity).despawn_recursive();
+        } else if let Some((rook_from, rook_to)) = castling_rook {
+            match is_forward {
+                true if piece.square == rook_from => piece.direction = rook_to,
+                false if piece.square == rook_to => piece.direction = rook_from,
+                _ => {}
+            }
+        }
+    }
+
+    if let (false, Some(captured_piece)) = (is_forward, captured_piece) {
+        spawn_piece_sprite(
+            &mut commands,
+            &asset_server,
+            captured_piece,
+            board_rotation(pgn.start_position.color_to_move),
+        );
+    }
+
+    replay.index = if is_forward { index + 1 } else { index };
+
+    // Game shows the replayed moves, so FEN and PGN export work on it too
+    *game = Game::new(pgn.start_position.clone());
+    for mv in &pgn.moves[..replay.index] {
+        game.make_move(*mv);
+    }
+    *turn = Turn::new(&game.position);
+}
@@ -0,0 +1,96 @@
+use crate::*;
+use std::{io::Write, num::NonZeroUsize, path::PathBuf};
+
+/// File the settings are kept in between sessions, one `key = value` per line
+const SETTINGS_FILE: &str = "settings.txt";
+
+/// Game settings chosen in the UI, loaded at start and saved whenever they change
+#[derive(Debug, Resource)]
+pub struct Settings {
+    pub difficulty: Difficulty,
+    /// Size of the AI's transposition table in MB
+    pub hash_size_mb: usize,
+    /// Threads the AI searches with, all cores by default, chosen in the UI
+    pub threads: usize,
+    /// UCI engine the AI uses instead of the built-in search
+    pub engine_path: Option<PathBuf>,
+    /// Polyglot opening book the AI plays from before searching
+    pub book_path: Option<PathBuf>,
+    /// Directories of Syzygy tablebase files, separated like `PATH`
+    pub syzygy_path: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::default(),
+            hash_size_mb: DEFAULT_HASH_SIZE_MB,
+            threads: std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            engine_path: None,
+            book_path: None,
+            syzygy_path: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings saved by the last session<br />
+    /// Missing file or values fall back to defaults, invalid values are printed, logging isn't set up yet
+    pub fn load() -> Self {
+        let mut settings: Settings = Settings::default();
+        let Ok(text) = std::fs::read_to_string(SETTINGS_FILE) else {
+            return settings;
+        };
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value): (&str, &str) = (key.trim(), value.trim());
+
+            match key {
+                "difficulty" => match Difficulty::from_name(value) {
+                    Some(difficulty) => settings.difficulty = difficulty,
+                    None => eprintln!("Unknown difficulty in {SETTINGS_FILE}: {value}"),
+                },
+                "hash_size_mb" => match value.parse() {
+                    Ok(size) if size >= 1 => settings.hash_size_mb = size,
+                    _ => eprintln!("Invalid hash size in {SETTINGS_FILE}: {value}"),
+                },
+                "threads" => match value.parse() {
+                    Ok(threads) if threads >= 1 => settings.threads = threads,
+                    _ => eprintln!("Invalid thread count in {SETTINGS_FILE}: {value}"),
+                },
+                "engine_path" if !value.is_empty() => settings.engine_path = Some(value.into()),
+                "engine_path" => settings.engine_path = None,
+                "book_path" if !value.is_empty() => settings.book_path = Some(value.into()),
+                "book_path" => settings.book_path = None,
+                "syzygy_path" if !value.is_empty() => settings.syzygy_path = Some(value.into()),
+                "syzygy_path" => settings.syzygy_path = None,
+                _ => eprintln!("Unknown setting in {SETTINGS_FILE}: {key}"),
+            }
+        }
+
+        settings
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut file: std::fs::File = std::fs::File::create(SETTINGS_FILE)?;
+        writeln!(file, "difficulty = {}", self.difficulty.name())?;
+        writeln!(file, "hash_size_mb = {}", self.hash_size_mb)?;
+        writeln!(file, "threads = {}", self.threads)?;
+        for (key, path) in [
+            ("engine_path", &self.engine_path),
+            ("book_path", &self.book_path),
+            ("syzygy_path", &self.syzygy_path),
+        ] {
+            writeln!(
+                file,
+                "{key} = {}",
+                path.as_ref()
+                    .map_or(String::new(), |path| path.display().to_string())
+            )?;
+        }
+        Ok(())
+    }
+}
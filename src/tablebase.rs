@@ -0,0 +1,123 @@
+use crate::*;
+use std::sync::Arc;
+
+pub struct TablebasePlugin;
+impl Plugin for TablebasePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(init_tablebase)
+            .add_startup_system(init_tablebase_ui)
+            .add_system(update_tablebase_ui);
+    }
+}
+
+/// Syzygy tablebase the AI and the readout probe, if one is configured<br />
+/// Shared with the AI's search task, tables load lazily on their first probe
+#[derive(Default, Resource)]
+pub struct SyzygyTablebase(pub Option<Arc<Tablebase>>);
+
+/// Opens the tablebase directories configured in the settings, nothing probes it if none has tables
+fn init_tablebase(mut commands: Commands, settings: Res<Settings>) {
+    let Some(path) = &settings.syzygy_path else {
+        commands.init_resource::<SyzygyTablebase>();
+        return;
+    };
+
+    let tablebase: Option<Arc<Tablebase>> = match Tablebase::open(path) {
+        Ok(tablebase) if tablebase.is_empty() => {
+            warn!("No Syzygy tables found in {}", path.display());
+            None
+        }
+        Ok(tablebase) => {
+            info!(
+                "Syzygy tablebase {} with {} tables up to {} pieces",
+                path.display(),
+                tablebase.len(),
+                tablebase.max_pieces()
+            );
+            Some(Arc::new(tablebase))
+        }
+        Err(error) => {
+            warn!(
+                "Couldn't read Syzygy tablebase {}: {}",
+                path.display(),
+                error
+            );
+            None
+        }
+    };
+    commands.insert_resource(SyzygyTablebase(tablebase));
+}
+
+#[derive(Component)]
+struct TablebaseText;
+
+fn init_tablebase_ui(mut commands: Commands, asset_server: ResMut<AssetServer>) {
+    let font: Handle<Font> = asset_server.load("fonts/UbuntuMonoNerdFontCompleteMono.ttf");
+
+    // Above the book indicator
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font,
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(125.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(TablebaseText);
+}
+
+/// Shows the tablebase result of the game's position when it has few enough pieces,
+/// e.g. "Tablebase: White wins, DTZ 12"
+fn update_tablebase_ui(
+    tablebase: Res<SyzygyTablebase>,
+    game: Res<Game>,
+    mut query: Query<&mut Text, With<TablebaseText>>,
+) {
+    if !game.is_changed() && !tablebase.is_changed() {
+        return;
+    }
+
+    let readout: String = tablebase
+        .0
+        .as_ref()
+        .and_then(|tablebase| tablebase_readout(tablebase, &game.position))
+        .unwrap_or_default();
+    for mut text in query.iter_mut() {
+        text.sections[0].value = readout.clone();
+    }
+}
+
+fn tablebase_readout(tablebase: &Tablebase, position: &Position) -> Option<String> {
+    let wdl: Wdl = tablebase.probe_wdl(position)?;
+    let color_name = |color: PieceColor| match color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    };
+    let to_move: &str = color_name(position.color_to_move);
+    let other: &str = color_name(position.color_to_move.opposite());
+
+    let result: String = match wdl {
+        Wdl::Win => format!("{to_move} wins"),
+        Wdl::CursedWin => format!("{to_move} wins, drawn by the fifty-move rule"),
+        Wdl::Draw => "draw".to_string(),
+        Wdl::BlessedLoss => format!("{other} wins, drawn by the fifty-move rule"),
+        Wdl::Loss => format!("{other} wins"),
+    };
+    // DTZ files are optional
+    Some(match tablebase.probe_dtz(position) {
+        Some(dtz) if wdl != Wdl::Draw => format!("Tablebase: {result}, DTZ {}", dtz.abs()),
+        _ => format!("Tablebase: {result}"),
+    })
+}
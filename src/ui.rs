@@ -10,7 +10,17 @@ impl Plugin for UIPlugin {
             .add_system(update_turn_ui)
             .add_system(show_captured_pieces)
             .add_system(update_material_advantage_ui)
-            .add_system(update_enable_ai_button_ui);
+            .add_system(update_enable_ai_button_ui)
+            .add_system(show_game_over_ui)
+            .add_system(show_promotion_dialog_ui)
+            .add_system(update_promotion_buttons_ui)
+            .add_system(copy_fen_button_ui)
+            .add_system(save_pgn_button_ui)
+            .add_startup_system(init_move_list_ui)
+            .add_system(update_move_list_ui)
+            .add_system(update_san_input_ui)
+            .add_startup_system(init_eval_bar_ui)
+            .add_system(update_eval_bar_ui);
     }
 }
 
@@ -20,13 +30,51 @@ struct NextMoveText;
 #[derive(Component)]
 struct MaterialAdvantageText;
 
+#[derive(Component)]
+struct GameOverText;
+
+/// Root node of the dialog for choosing a piece to promote a pawn to
+#[derive(Component)]
+struct PromotionDialog;
+
+/// Button promoting the pending pawn to the given piece type
+#[derive(Component)]
+struct PromotionButton(PieceType);
+
 #[derive(Component)]
 pub struct AIEnabled(pub bool);
 
+/// Button copying the FEN of the current position to the clipboard
+#[derive(Component)]
+struct CopyFENButton;
+
+/// Button saving the game played so far as PGN
+#[derive(Component)]
+struct SavePGNButton;
+
+#[derive(Component)]
+struct MoveListText;
+
+#[derive(Component)]
+struct SanInputText;
+
+/// White part of the eval bar, grows from the bottom with White's advantage
+#[derive(Component)]
+struct EvalBarFill;
+
+#[derive(Component)]
+struct EvalText;
+
 const AI_BUTTON_ENABLED: BackgroundColor = BackgroundColor(Color::rgb(0.35, 0.75, 0.35));
 const AI_BUTTON_ENABLED_HOVER: BackgroundColor = BackgroundColor(Color::rgb(0.45, 0.85, 0.45));
 const AI_BUTTON_DISABLED: BackgroundColor = BackgroundColor(Color::rgb(0.15, 0.15, 0.15));
 const AI_BUTTON_DISABLED_HOVER: BackgroundColor = BackgroundColor(Color::rgb(0.25, 0.25, 0.25));
+const PROMOTION_BUTTON: BackgroundColor = BackgroundColor(Color::rgb(0.15, 0.15, 0.15));
+const PROMOTION_BUTTON_HOVER: BackgroundColor = BackgroundColor(Color::rgb(0.25, 0.25, 0.25));
+const COPY_FEN_BUTTON: BackgroundColor = BackgroundColor(Color::rgb(0.15, 0.15, 0.15));
+const COPY_FEN_BUTTON_HOVER: BackgroundColor = BackgroundColor(Color::rgb(0.25, 0.25, 0.25));
+const SAVE_PGN_BUTTON: BackgroundColor = BackgroundColor(Color::rgb(0.15, 0.15, 0.15));
+const SAVE_PGN_BUTTON_HOVER: BackgroundColor = BackgroundColor(Color::rgb(0.25, 0.25, 0.25));
 
 fn init_show_ui(mut commands: Commands, asset_server: ResMut<AssetServer>, turn: Res<Turn>) {
     let font: Handle<Font> = asset_server.load("fonts/UbuntuMonoNerdFontCompleteMono.ttf");
@@ -104,13 +152,247 @@ fn init_show_ui(mut commands: Commands, asset_server: ResMut<AssetServer>, turn:
             parent.spawn(TextBundle::from_section(
                 "AI disabled",
                 TextStyle {
-                    font,
+                    font: font.clone(),
                     font_size: 20.0,
                     color: Color::WHITE,
                 },
             ));
         })
         .insert(AIEnabled(false));
+
+    // Copy FEN button
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                align_self: AlignSelf::FlexEnd,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(5.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                },
+                ..default()
+            },
+            background_color: COPY_FEN_BUTTON,
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Copy FEN",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            ));
+        })
+        .insert(CopyFENButton);
+
+    // Save PGN button
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                align_self: AlignSelf::FlexEnd,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(35.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                },
+                ..default()
+            },
+            background_color: SAVE_PGN_BUTTON,
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Save PGN",
+                TextStyle {
+                    font,
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            ));
+        })
+        .insert(SavePGNButton);
+}
+
+/// Spawns the list of played moves and the move typed in SAN below it
+fn init_move_list_ui(mut commands: Commands, asset_server: ResMut<AssetServer>) {
+    let font: Handle<Font> = asset_server.load("fonts/UbuntuMonoNerdFontCompleteMono.ttf");
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(100.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                gap: Size::height(Val::Px(10.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(MoveListText);
+
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(SanInputText);
+        });
+}
+
+/// Number of last full moves shown in the move list
+const MOVE_LIST_LENGTH: usize = 16;
+
+/// Shows the last moves in SAN, one full move per line, e.g. `12. Nbd7  exd6`
+fn update_move_list_ui(game: Res<Game>, mut query: Query<&mut Text, With<MoveListText>>) {
+    if !game.is_changed() {
+        return;
+    }
+
+    // Black moving first leaves the white half of the first line empty
+    let mut moves: Vec<String> = game.san_moves();
+    if game.start_position.color_to_move == PieceColor::Black {
+        moves.insert(0, "...".to_string());
+    }
+
+    let lines: Vec<String> = moves
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            format!(
+                "{:>3}. {:<8}{}",
+                game.start_position.fullmove_number as usize + i,
+                pair[0],
+                pair.get(1).map_or("", |san| san.as_str())
+            )
+        })
+        .collect();
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = lines[lines.len().saturating_sub(MOVE_LIST_LENGTH)..].join("\n");
+    }
+}
+
+/// Shows the move the player is typing in SAN
+fn update_san_input_ui(san_input: Res<SanInput>, mut query: Query<&mut Text, With<SanInputText>>) {
+    if !san_input.is_changed() {
+        return;
+    }
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = if san_input.0.is_empty() {
+            String::new()
+        } else {
+            format!("> {}", san_input.0)
+        };
+    }
+}
+
+/// Spawns the eval bar left of the board, with the score in pawns above it
+fn init_eval_bar_ui(mut commands: Commands, asset_server: ResMut<AssetServer>) {
+    let font: Handle<Font> = asset_server.load("fonts/UbuntuMonoNerdFontCompleteMono.ttf");
+    let board_size: f32 = SQUARE_SIZE * N_OF_SQUARES as f32;
+    let board_top: f32 = (WINDOW_HEIGHT as f32 - board_size) / 2.0;
+    let board_left: f32 = (WINDOW_WIDTH as f32 - board_size) / 2.0;
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(board_top),
+                    left: Val::Px(board_left - 30.0),
+                    ..default()
+                },
+                size: Size::new(Val::Px(20.0), Val::Px(board_size)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::FlexEnd,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::rgb(0.1, 0.1, 0.1)),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(50.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.9, 0.9, 0.9)),
+                    ..default()
+                })
+                .insert(EvalBarFill);
+        });
+
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "0.00",
+                TextStyle {
+                    font,
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(board_top - 20.0),
+                    left: Val::Px(board_left - 40.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(EvalText);
+}
+
+/// Shows the static evaluation of the position from White's point of view
+fn update_eval_bar_ui(
+    game: Res<Game>,
+    mut fill_query: Query<&mut Style, With<EvalBarFill>>,
+    mut text_query: Query<&mut Text, With<EvalText>>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+
+    let score: i32 = match game.position.color_to_move {
+        PieceColor::White => evaluate(&game.position),
+        PieceColor::Black => -evaluate(&game.position),
+    };
+
+    // Expected score of White like in Elo, a pawn up fills about 64% of the bar
+    let white_share: f32 = 1.0 / (1.0 + 10f32.powf(-score as f32 / 400.0));
+
+    for mut style in fill_query.iter_mut() {
+        style.size.height = Val::Percent(white_share * 100.0);
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!("{:+.2}", score as f32 / PAWN_VALUE as f32);
+    }
 }
 
 fn update_turn_ui(turn: Res<Turn>, mut query: Query<&mut Text, With<NextMoveText>>) {
@@ -131,14 +413,14 @@ fn update_turn_ui(turn: Res<Turn>, mut query: Query<&mut Text, With<NextMoveText
 #[derive(Component)]
 struct CapturedSideBoard;
 
-static mut WHITE_CAPTURED_PIECES: Mutex<Vec<PieceType>> = Mutex::new(Vec::new());
-static mut BLACK_CAPTURED_PIECES: Mutex<Vec<PieceType>> = Mutex::new(Vec::new());
+static WHITE_CAPTURED_PIECES: Mutex<Vec<PieceType>> = Mutex::new(Vec::new());
+static BLACK_CAPTURED_PIECES: Mutex<Vec<PieceType>> = Mutex::new(Vec::new());
 
 /// This system shows the captured pieces on the side of the board
 fn show_captured_pieces(
     mut commands: Commands,
     asset_server: ResMut<AssetServer>,
-    captured_pieces_query: Query<&Piece, With<Captured>>,
+    captured_pieces_query: Query<&PieceSprite, With<Captured>>,
     captured_pieces_side_board_query: Query<Entity, With<CapturedSideBoard>>,
     turn: Res<Turn>,
 ) {
@@ -152,19 +434,13 @@ fn show_captured_pieces(
 
     for piece in captured_pieces_query.iter() {
         match piece.color {
-            PieceColor::White => unsafe {
-                WHITE_CAPTURED_PIECES.lock().unwrap().push(piece.piece_type)
-            },
-            PieceColor::Black => unsafe {
-                BLACK_CAPTURED_PIECES.lock().unwrap().push(piece.piece_type)
-            },
+            PieceColor::White => WHITE_CAPTURED_PIECES.lock().unwrap().push(piece.piece_type),
+            PieceColor::Black => BLACK_CAPTURED_PIECES.lock().unwrap().push(piece.piece_type),
         };
     }
 
-    unsafe {
-        WHITE_CAPTURED_PIECES.lock().unwrap().sort();
-        BLACK_CAPTURED_PIECES.lock().unwrap().sort();
-    }
+    WHITE_CAPTURED_PIECES.lock().unwrap().sort();
+    BLACK_CAPTURED_PIECES.lock().unwrap().sort();
 
     macro_rules! load_piece {
         ($piece:ident) => {
@@ -194,12 +470,7 @@ fn show_captured_pieces(
         PieceColor::Black => Quat::from_rotation_z(std::f32::consts::PI),
     };
 
-    for (i, piece) in unsafe { &WHITE_CAPTURED_PIECES }
-        .lock()
-        .unwrap()
-        .iter()
-        .enumerate()
-    {
+    for (i, piece) in WHITE_CAPTURED_PIECES.lock().unwrap().iter().enumerate() {
         let piece_pos: Vec3 = Vec3::new(-3.8 * square_size + i as f32 * 16., 4.2 * square_size, 0.);
         commands
             .spawn(SpriteBundle {
@@ -220,12 +491,7 @@ fn show_captured_pieces(
             })
             .insert(CapturedSideBoard);
     }
-    for (i, piece) in unsafe { &BLACK_CAPTURED_PIECES }
-        .lock()
-        .unwrap()
-        .iter()
-        .enumerate()
-    {
+    for (i, piece) in BLACK_CAPTURED_PIECES.lock().unwrap().iter().enumerate() {
         let piece_pos: Vec3 = Vec3::new(3.8 * square_size - i as f32 * 16., -4.2 * square_size, 0.);
         commands
             .spawn(SpriteBundle {
@@ -256,10 +522,8 @@ fn update_material_advantage_ui(
         return;
     }
 
-    let white_captured_pieces: MutexGuard<Vec<PieceType>> =
-        unsafe { &WHITE_CAPTURED_PIECES }.lock().unwrap();
-    let black_captured_pieces: MutexGuard<Vec<PieceType>> =
-        unsafe { &BLACK_CAPTURED_PIECES }.lock().unwrap();
+    let white_captured_pieces: MutexGuard<Vec<PieceType>> = WHITE_CAPTURED_PIECES.lock().unwrap();
+    let black_captured_pieces: MutexGuard<Vec<PieceType>> = BLACK_CAPTURED_PIECES.lock().unwrap();
 
     // Calculate material advantage
     let material_advantage: i8 = black_captured_pieces
@@ -288,6 +552,8 @@ fn update_enable_ai_button_ui(
         (Changed<Interaction>, With<AIEnabled>),
     >,
     mut text_query: Query<&mut Text>,
+    mut ai_color: ResMut<AIColor>,
+    game: Res<Game>,
 ) {
     for (interaction, mut color, children, mut ai_enabled) in interaction_query.iter_mut() {
         let mut text = text_query.get_mut(children[0]).unwrap();
@@ -302,6 +568,8 @@ fn update_enable_ai_button_ui(
                     color.0 = AI_BUTTON_ENABLED.0;
                     text.sections[0].value = "AI enabled".to_string();
                     ai_enabled.0 = true;
+                    // Player to move keeps playing, AI takes the other side
+                    ai_color.0 = game.position.color_to_move.opposite();
                 }
             },
             Interaction::Hovered => match ai_enabled.0 {
@@ -323,3 +591,242 @@ fn update_enable_ai_button_ui(
         }
     }
 }
+
+/// Shows the result of the game in the middle of the screen when the game ends
+fn show_game_over_ui(
+    mut commands: Commands,
+    asset_server: ResMut<AssetServer>,
+    mut game_over_events: EventReader<GameOverEvent>,
+) {
+    for game_over_event in game_over_events.iter() {
+        let text: String = match game_over_event.result {
+            GameResult::Checkmate { winner } => format!("Checkmate!\n{:?} won", winner),
+            GameResult::Stalemate => "Stalemate!\nDraw".to_string(),
+            GameResult::FiftyMoveRule => "Fifty-move rule!\nDraw".to_string(),
+            GameResult::ThreefoldRepetition => "Threefold repetition!\nDraw".to_string(),
+            GameResult::InsufficientMaterial => "Insufficient material!\nDraw".to_string(),
+        };
+
+        commands
+            .spawn(TextBundle {
+                style: Style {
+                    align_self: AlignSelf::Center,
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(5.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                text: Text::from_section(
+                    text,
+                    TextStyle {
+                        font: asset_server.load("fonts/UbuntuMonoNerdFontCompleteMono.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(0.85, 0.25, 0.25),
+                    },
+                ),
+                ..default()
+            })
+            .insert(GameOverText);
+    }
+}
+
+/// Shows buttons for choosing the piece when a pawn reaches the last row
+fn show_promotion_dialog_ui(
+    mut commands: Commands,
+    asset_server: ResMut<AssetServer>,
+    pending_promotion: Res<PendingPromotion>,
+    pieces_query: Query<&PieceSprite>,
+) {
+    if !pending_promotion.is_changed() {
+        return;
+    }
+
+    let piece: &PieceSprite = if let Some(Ok(piece)) = pending_promotion
+        .entity
+        .map(|entity| pieces_query.get(entity))
+    {
+        piece
+    } else {
+        return;
+    };
+
+    let font: Handle<Font> = asset_server.load("fonts/UbuntuMonoNerdFontCompleteMono.ttf");
+    let choices: [(PieceType, &str); 4] = match piece.color {
+        PieceColor::White => [
+            (PieceType::QueenWhite, "Queen"),
+            (PieceType::RookWhite, "Rook"),
+            (PieceType::BishopWhite, "Bishop"),
+            (PieceType::KnightWhite, "Knight"),
+        ],
+        PieceColor::Black => [
+            (PieceType::QueenBlack, "Queen"),
+            (PieceType::RookBlack, "Rook"),
+            (PieceType::BishopBlack, "Bishop"),
+            (PieceType::KnightBlack, "Knight"),
+        ],
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                align_self: AlignSelf::FlexEnd,
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                position: UiRect {
+                    top: Val::Px(100.0),
+                    left: Val::Px(5.0),
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Promote to:",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            for (piece_type, name) in choices {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            margin: UiRect::top(Val::Px(5.0)),
+                            ..default()
+                        },
+                        background_color: PROMOTION_BUTTON,
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            name,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                            },
+                        ));
+                    })
+                    .insert(PromotionButton(piece_type));
+            }
+        })
+        .insert(PromotionDialog);
+}
+
+/// Promotes the pending pawn to the chosen piece and finishes the turn
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn update_promotion_buttons_ui(
+    mut commands: Commands,
+    asset_server: ResMut<AssetServer>,
+    mut pending_promotion: ResMut<PendingPromotion>,
+    mut turn_end_events: EventWriter<TurnEndEvent>,
+    mut game: ResMut<Game>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &PromotionButton),
+        Changed<Interaction>,
+    >,
+    mut pieces_query: Query<(&mut PieceSprite, &mut Handle<Image>)>,
+    dialog_query: Query<Entity, With<PromotionDialog>>,
+) {
+    for (interaction, mut color, promotion_button) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                let (entity, mv): (Entity, Move) = if let (Some(entity), Some(mv)) =
+                    (pending_promotion.entity, pending_promotion.mv)
+                {
+                    (entity, mv)
+                } else {
+                    continue;
+                };
+
+                if let Ok((mut piece, mut texture)) = pieces_query.get_mut(entity) {
+                    info!("Promoted piece to {:?}", promotion_button.0);
+                    piece.piece_type = promotion_button.0;
+                    *texture = asset_server.load(get_image_path(promotion_button.0));
+                }
+
+                for dialog in dialog_query.iter() {
+                    commands.entity(dialog).despawn_recursive();
+                }
+
+                game.make_move(Move {
+                    promotion: Some(promotion_button.0),
+                    ..mv
+                });
+                pending_promotion.entity = None;
+                pending_promotion.mv = None;
+                turn_end_events.send(TurnEndEvent);
+            }
+            Interaction::Hovered => color.0 = PROMOTION_BUTTON_HOVER.0,
+            Interaction::None => color.0 = PROMOTION_BUTTON.0,
+        }
+    }
+}
+
+/// Copies the FEN of the current position to the clipboard when the button is clicked<br />
+/// Clipboard is kept alive, on some platforms the copied text disappears with it
+#[allow(clippy::type_complexity)]
+fn copy_fen_button_ui(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<CopyFENButton>),
+    >,
+    game: Res<Game>,
+    mut clipboard: Local<Option<arboard::Clipboard>>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                let fen: String = game.position.to_fen();
+
+                if clipboard.is_none() {
+                    match arboard::Clipboard::new() {
+                        Ok(new_clipboard) => *clipboard = Some(new_clipboard),
+                        Err(error) => {
+                            warn!("Clipboard not available: {}", error);
+                            return;
+                        }
+                    }
+                }
+                let Some(clipboard) = clipboard.as_mut() else {
+                    return;
+                };
+
+                match clipboard.set_text(fen.clone()) {
+                    Ok(()) => info!("FEN copied to clipboard: {}", fen),
+                    Err(error) => warn!("Couldn't copy FEN to clipboard: {}", error),
+                }
+            }
+            Interaction::Hovered => color.0 = COPY_FEN_BUTTON_HOVER.0,
+            Interaction::None => color.0 = COPY_FEN_BUTTON.0,
+        }
+    }
+}
+
+/// Saves the game played so far as PGN when the button is clicked
+#[allow(clippy::type_complexity)]
+fn save_pgn_button_ui(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<SavePGNButton>),
+    >,
+    game: Res<Game>,
+    game_over: Res<GameOver>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Clicked => match game.save_pgn(game_over.result) {
+                Ok(path) => info!("Game saved to {}", path.display()),
+                Err(error) => warn!("Couldn't save game: {}", error),
+            },
+            Interaction::Hovered => color.0 = SAVE_PGN_BUTTON_HOVER.0,
+            Interaction::None => color.0 = SAVE_PGN_BUTTON.0,
+        }
+    }
+}